@@ -3,7 +3,11 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 
 use cosmwasm_chess::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
-use cosmwasm_chess::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg};
+use cosmwasm_chess::msg::{
+    ExecuteMsg, GameClock, GameSummary, InstantiateMsg, LeaderboardPage, PlayerSummary, QueryMsg,
+    ReceiveMsg,
+};
+use cosmwasm_chess::state::PlayerStats;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -16,7 +20,12 @@ fn main() {
     export_schema(&schema_for!(CwChessGame), &out_dir);
     export_schema(&schema_for!(CwChessGameOver), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(GameClock), &out_dir);
     export_schema(&schema_for!(GameSummary), &out_dir);
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
+    export_schema(&schema_for!(LeaderboardPage), &out_dir);
+    export_schema(&schema_for!(PlayerStats), &out_dir);
+    export_schema(&schema_for!(PlayerSummary), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
 }
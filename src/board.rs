@@ -0,0 +1,283 @@
+use crate::cwchess::CwChessColor;
+use crate::fen;
+
+// Minimal board for checking attacks and legal moves.
+//
+// chess-engine only reports checkmate and stalemate after moves it makes itself,
+// so positions reached by moves the contract makes (Chess960 castling) are checked here.
+//
+// squares are numbered 0 (a8) through 63 (h1), in FEN order
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    pub en_passant: Option<usize>,
+    pub squares: [Option<char>; 64],
+    pub turn_color: CwChessColor,
+}
+
+impl Board {
+    pub fn from_fen(fen: &str) -> Option<Board> {
+        let mut squares = [None; 64];
+        for (square, piece) in fen::pieces(fen) {
+            if square >= 64 {
+                return None;
+            }
+            squares[square] = Some(piece);
+        }
+        let en_passant = match fen.split(' ').nth(3) {
+            None | Some("-") => None,
+            Some(square) => Some(parse_square(square)?),
+        };
+        Some(Board {
+            en_passant,
+            squares,
+            turn_color: fen::turn_color(fen)?,
+        })
+    }
+
+    // check whether any piece of color attacks square
+    pub fn is_attacked(&self, square: usize, by: &CwChessColor) -> bool {
+        let is_piece = |offset: (i8, i8), pieces: &str| -> bool {
+            match self.offset_piece(square, offset) {
+                Some(piece) => is_color(piece, by) && pieces.contains(piece),
+                None => false,
+            }
+        };
+        // pawns attack toward opposite side of board
+        let pawn_rank = match by {
+            CwChessColor::White => 1,
+            CwChessColor::Black => -1,
+        };
+        if is_piece((pawn_rank, -1), "Pp") || is_piece((pawn_rank, 1), "Pp") {
+            return true;
+        }
+        if KNIGHT_OFFSETS.iter().any(|o| is_piece(*o, "Nn")) {
+            return true;
+        }
+        if KING_OFFSETS.iter().any(|o| is_piece(*o, "Kk")) {
+            return true;
+        }
+        let is_slider = |directions: &[(i8, i8)], pieces: &str| -> bool {
+            directions
+                .iter()
+                .any(|direction| match self.slide(square, *direction).last() {
+                    Some(target) => match self.squares[*target] {
+                        Some(piece) => is_color(piece, by) && pieces.contains(piece),
+                        None => false,
+                    },
+                    None => false,
+                })
+        };
+        is_slider(&BISHOP_DIRECTIONS, "BbQq") || is_slider(&ROOK_DIRECTIONS, "RrQq")
+    }
+
+    // check whether player to move is in check
+    pub fn is_check(&self) -> bool {
+        match self.king_square(&self.turn_color) {
+            Some(square) => self.is_attacked(square, &opposite(&self.turn_color)),
+            None => false,
+        }
+    }
+
+    // check whether player to move has any legal move, not including castling
+    pub fn has_legal_move(&self) -> bool {
+        (0..64)
+            .filter(|from| match self.squares[*from] {
+                Some(piece) => is_color(piece, &self.turn_color),
+                None => false,
+            })
+            .any(|from| {
+                self.get_targets(from).into_iter().any(|to| {
+                    let board = self.after_move(from, to);
+                    match board.king_square(&self.turn_color) {
+                        Some(king) => !board.is_attacked(king, &board.turn_color),
+                        None => false,
+                    }
+                })
+            })
+    }
+
+    pub fn king_square(&self, color: &CwChessColor) -> Option<usize> {
+        let king = match color {
+            CwChessColor::White => 'K',
+            CwChessColor::Black => 'k',
+        };
+        self.squares.iter().position(|s| *s == Some(king))
+    }
+
+    // piece placement part of FEN
+    pub fn to_placement(&self) -> String {
+        let mut placement = String::new();
+        for rank in 0..8 {
+            if rank > 0 {
+                placement.push('/');
+            }
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.squares[rank * 8 + file] {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece);
+                    }
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+        }
+        placement
+    }
+
+    // board after piece moves (promotion and castling are not needed to check legality)
+    fn after_move(&self, from: usize, to: usize) -> Board {
+        let mut board = self.clone();
+        let piece = board.squares[from].take();
+        // en passant capture removes pawn beside target
+        if Some(to) == self.en_passant && (piece == Some('P') || piece == Some('p')) {
+            board.squares[(from / 8) * 8 + to % 8] = None;
+        }
+        board.squares[to] = piece;
+        board.en_passant = None;
+        board.turn_color = opposite(&self.turn_color);
+        board
+    }
+
+    // squares a piece can move to, ignoring whether king is left in check
+    fn get_targets(&self, from: usize) -> Vec<usize> {
+        let piece = match self.squares[from] {
+            Some(piece) => piece,
+            None => return vec![],
+        };
+        let is_empty = |square: &usize| self.squares[*square].is_none();
+        let is_capture = |square: &usize| match self.squares[*square] {
+            Some(target) => !is_color(target, &self.turn_color),
+            None => false,
+        };
+        let steps = |offsets: &[(i8, i8)]| -> Vec<usize> {
+            offsets
+                .iter()
+                .filter_map(|offset| offset_square(from, *offset))
+                .filter(|square| is_empty(square) || is_capture(square))
+                .collect()
+        };
+        let slides = |directions: &[(i8, i8)]| -> Vec<usize> {
+            directions
+                .iter()
+                .flat_map(|direction| self.slide(from, *direction))
+                .filter(|square| is_empty(square) || is_capture(square))
+                .collect()
+        };
+        match piece.to_ascii_uppercase() {
+            'P' => {
+                let (forward, start_rank) = match self.turn_color {
+                    CwChessColor::White => (-1, 6),
+                    CwChessColor::Black => (1, 1),
+                };
+                let mut targets = vec![];
+                if let Some(one) = offset_square(from, (forward, 0)).filter(is_empty) {
+                    targets.push(one);
+                    if from / 8 == start_rank {
+                        if let Some(two) = offset_square(one, (forward, 0)).filter(is_empty) {
+                            targets.push(two);
+                        }
+                    }
+                }
+                for side in [-1, 1] {
+                    if let Some(target) = offset_square(from, (forward, side)) {
+                        if is_capture(&target) || Some(target) == self.en_passant {
+                            targets.push(target);
+                        }
+                    }
+                }
+                targets
+            }
+            'N' => steps(&KNIGHT_OFFSETS),
+            'B' => slides(&BISHOP_DIRECTIONS),
+            'R' => slides(&ROOK_DIRECTIONS),
+            'Q' => [slides(&BISHOP_DIRECTIONS), slides(&ROOK_DIRECTIONS)].concat(),
+            'K' => steps(&KING_OFFSETS),
+            _ => vec![],
+        }
+    }
+
+    fn offset_piece(&self, square: usize, offset: (i8, i8)) -> Option<char> {
+        offset_square(square, offset).and_then(|square| self.squares[square])
+    }
+
+    // squares in a direction, up to and including first occupied square
+    fn slide(&self, from: usize, direction: (i8, i8)) -> Vec<usize> {
+        let mut squares = vec![];
+        let mut square = from;
+        while let Some(next) = offset_square(square, direction) {
+            squares.push(next);
+            if self.squares[next].is_some() {
+                break;
+            }
+            square = next;
+        }
+        squares
+    }
+}
+
+pub fn is_color(piece: char, color: &CwChessColor) -> bool {
+    match color {
+        CwChessColor::White => piece.is_ascii_uppercase(),
+        CwChessColor::Black => piece.is_ascii_lowercase(),
+    }
+}
+
+pub fn opposite(color: &CwChessColor) -> CwChessColor {
+    match color {
+        CwChessColor::White => CwChessColor::Black,
+        CwChessColor::Black => CwChessColor::White,
+    }
+}
+
+// square offset by (ranks, files), None if off the board
+fn offset_square(square: usize, offset: (i8, i8)) -> Option<usize> {
+    let rank = (square / 8) as i8 + offset.0;
+    let file = (square % 8) as i8 + offset.1;
+    if (0..8).contains(&rank) && (0..8).contains(&file) {
+        Some((rank * 8 + file) as usize)
+    } else {
+        None
+    }
+}
+
+// parse square name (e.g. "e3")
+fn parse_square(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let file = chars.next()? as i32 - 'a' as i32;
+    let rank = chars.next()? as i32 - '1' as i32;
+    if chars.next().is_some() || !(0..8).contains(&file) || !(0..8).contains(&rank) {
+        return None;
+    }
+    Some(((7 - rank) * 8 + file) as usize)
+}
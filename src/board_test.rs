@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::cwchess::CwChessColor;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_from_fen() {
+        let board = Board::from_fen(START).unwrap();
+        assert_eq!(board.squares[0], Some('r'));
+        assert_eq!(board.squares[63], Some('R'));
+        assert_eq!(board.turn_color, CwChessColor::White);
+        assert_eq!(board.to_placement(), START.split(' ').next().unwrap());
+        // en passant square
+        let board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(board.en_passant, Some(44));
+        assert_eq!(Board::from_fen("8/8/8/8/8/8/8/8 x - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_is_attacked() {
+        let board = Board::from_fen(START).unwrap();
+        // e3 by white pawns, f3 by knight
+        assert!(board.is_attacked(44, &CwChessColor::White));
+        assert!(board.is_attacked(45, &CwChessColor::White));
+        assert!(!board.is_attacked(36, &CwChessColor::White));
+        // e6 by black pawns
+        assert!(board.is_attacked(20, &CwChessColor::Black));
+        assert!(!board.is_attacked(44, &CwChessColor::Black));
+        // sliding pieces are blocked
+        let board = Board::from_fen("4k3/8/8/q7/8/8/3P4/4K3 w - - 0 1").unwrap();
+        assert!(!board.is_check());
+        let board = Board::from_fen("4k3/8/8/q7/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(board.is_check());
+    }
+
+    #[test]
+    fn test_has_legal_move() {
+        assert!(Board::from_fen(START).unwrap().has_legal_move());
+        // checkmate
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(board.is_check());
+        assert!(!board.has_legal_move());
+        // stalemate
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!board.is_check());
+        assert!(!board.has_legal_move());
+        // capture escapes check
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/r5K1 b - - 0 1").unwrap();
+        assert!(board.has_legal_move());
+    }
+}
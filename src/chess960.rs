@@ -0,0 +1,243 @@
+use chess_engine::{Game, GameAction};
+use std::convert::TryFrom;
+
+use crate::board::{is_color, opposite, Board};
+use crate::cwchess::{CwChessAction, CwChessColor, CwChessGameOver};
+use crate::error::ContractError;
+use crate::fen;
+
+// Chess960 (Fischer Random) support.
+//
+// Castling rights are stored in the game FEN using Shredder-FEN notation (the files of
+// rooks that can castle, uppercase for white), and removed when loading positions into
+// chess-engine, which only knows standard castling. Castling moves are made here instead.
+
+pub const CHESS960_POSITIONS: u16 = 960;
+
+// knight squares, as indexes of the 5 squares left after bishops and queen are placed
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+pub enum CastlingSide {
+    King,
+    Queen,
+}
+
+// white back rank for a starting position number (0-959), standard chess is 518
+pub fn back_rank(position: u16) -> [char; 8] {
+    let mut rank = [' '; 8];
+    let n = (position % CHESS960_POSITIONS) as usize;
+    // bishops on opposite color squares
+    rank[(n % 4) * 2 + 1] = 'B';
+    let n = n / 4;
+    rank[(n % 4) * 2] = 'B';
+    let n = n / 4;
+    // queen, then knights on remaining squares
+    let empty = empty_files(&rank);
+    rank[empty[n % 6]] = 'Q';
+    let (knight1, knight2) = KNIGHTS[n / 6];
+    let empty = empty_files(&rank);
+    rank[empty[knight1]] = 'N';
+    rank[empty[knight2]] = 'N';
+    // king between rooks
+    let empty = empty_files(&rank);
+    rank[empty[0]] = 'R';
+    rank[empty[1]] = 'K';
+    rank[empty[2]] = 'R';
+    rank
+}
+
+// check whether player to move can castle
+pub fn can_castle(fen: &str) -> bool {
+    match Board::from_fen(fen) {
+        None => false,
+        Some(board) => {
+            let rights = fen::castling(fen);
+            castle_board(&board, rights, &CastlingSide::King).is_some()
+                || castle_board(&board, rights, &CastlingSide::Queen).is_some()
+        }
+    }
+}
+
+// castle for player to move
+// returns status and position after castling
+pub fn castle(
+    fen: &str,
+    side: &CastlingSide,
+) -> Result<(Option<CwChessGameOver>, String), ContractError> {
+    let board = Board::from_fen(fen).ok_or(ContractError::InvalidPosition {})?;
+    let rights = fen::castling(fen);
+    let board = castle_board(&board, rights, side).ok_or(ContractError::InvalidMove {})?;
+    // castling uses up both rights for player
+    let rights = rights
+        .chars()
+        .filter(|right| is_color(*right, &board.turn_color))
+        .collect::<String>();
+    let fen = format!(
+        "{} {} {} - 0 1",
+        board.to_placement(),
+        match board.turn_color {
+            CwChessColor::White => "w",
+            CwChessColor::Black => "b",
+        },
+        if rights.is_empty() { "-" } else { &rights },
+    );
+    // chess-engine only detects checkmate and stalemate for its own moves
+    let status = if board.has_legal_move() || can_castle(&fen) {
+        None
+    } else if !board.is_check() {
+        Some(CwChessGameOver::Stalemate)
+    } else {
+        match board.turn_color {
+            CwChessColor::White => Some(CwChessGameOver::BlackCheckmates),
+            CwChessColor::Black => Some(CwChessGameOver::WhiteCheckmates),
+        }
+    };
+    Ok((status, fen))
+}
+
+// get castling side for castling moves ("O-O" or "O-O-O")
+pub fn castling_side(action: &CwChessAction) -> Option<CastlingSide> {
+    match action {
+        CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) => {
+            match move_str.trim_end_matches(&['+', '#'][..]) {
+                "O-O" | "0-0" => Some(CastlingSide::King),
+                "O-O-O" | "0-0-0" => Some(CastlingSide::Queen),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// make move in a Chess960 game
+// returns status and position after move
+pub fn make_move(
+    fen: &str,
+    game: &mut Game,
+    action: &CwChessAction,
+) -> Result<(Option<CwChessGameOver>, String), ContractError> {
+    if let Some(side) = castling_side(action) {
+        return castle(fen, &side);
+    }
+    let status = match game.make_move(&GameAction::try_from(action)?) {
+        Ok(status) => status.as_ref().map(CwChessGameOver::from),
+        Err(_) => return Err(ContractError::InvalidMove {}),
+    };
+    let next_fen = game.to_fen(0, 0).unwrap();
+    let next_fen = fen::with_castling(&next_fen, &update_castling(fen, &next_fen));
+    // chess-engine doesn't know when castling is still possible
+    let status = match status {
+        Some(CwChessGameOver::Stalemate) if can_castle(&next_fen) => None,
+        status => status,
+    };
+    Ok((status, next_fen))
+}
+
+// starting position in FEN, with Shredder-FEN castling rights
+pub fn start_fen(position: u16) -> String {
+    let white = back_rank(position).iter().collect::<String>();
+    let black = white.to_ascii_lowercase();
+    // rook files, king side first
+    let rooks = white
+        .char_indices()
+        .filter(|(_, piece)| *piece == 'R')
+        .map(|(file, _)| (b'a' + file as u8) as char)
+        .rev()
+        .collect::<String>();
+    format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+        black,
+        white,
+        rooks.to_ascii_uppercase(),
+        rooks
+    )
+}
+
+// remove castling rights when king moves, or rook moves or is captured
+pub fn update_castling(before: &str, after: &str) -> String {
+    let (before_board, after_board) = match (Board::from_fen(before), Board::from_fen(after)) {
+        (Some(before_board), Some(after_board)) => (before_board, after_board),
+        _ => return "-".to_string(),
+    };
+    let rights = fen::castling(before)
+        .chars()
+        .filter(|right| {
+            let (color, rook, rank) = match right.is_ascii_uppercase() {
+                true => (CwChessColor::White, 'R', 7),
+                false => (CwChessColor::Black, 'r', 0),
+            };
+            let file = (right.to_ascii_lowercase() as u8).wrapping_sub(b'a') as usize;
+            file < 8
+                && before_board.king_square(&color) == after_board.king_square(&color)
+                && after_board.squares[rank * 8 + file] == Some(rook)
+        })
+        .collect::<String>();
+    if rights.is_empty() {
+        "-".to_string()
+    } else {
+        rights
+    }
+}
+
+// board after castling, None if castling is not allowed
+fn castle_board(board: &Board, rights: &str, side: &CastlingSide) -> Option<Board> {
+    let color = board.turn_color.clone();
+    let (king, rook, rank) = match color {
+        CwChessColor::White => ('K', 'R', 7),
+        CwChessColor::Black => ('k', 'r', 0),
+    };
+    let king_from = board.king_square(&color).filter(|s| s / 8 == rank)?;
+    let rook_from = rights
+        .chars()
+        .filter(|right| is_color(*right, &color))
+        .map(|right| rank * 8 + (right.to_ascii_lowercase() as u8).wrapping_sub(b'a') as usize)
+        .find(|square| match side {
+            CastlingSide::King => *square > king_from && *square < rank * 8 + 8,
+            CastlingSide::Queen => *square < king_from && *square >= rank * 8,
+        })?;
+    if board.squares[rook_from] != Some(rook) || board.is_check() {
+        return None;
+    }
+    // king and rook end on same squares as standard castling
+    let (king_to, rook_to) = match side {
+        CastlingSide::King => (rank * 8 + 6, rank * 8 + 5),
+        CastlingSide::Queen => (rank * 8 + 2, rank * 8 + 3),
+    };
+    let mut castled = board.clone();
+    castled.squares[king_from] = None;
+    castled.squares[rook_from] = None;
+    // squares king and rook move across must be empty
+    let squares = [king_from, king_to, rook_from, rook_to];
+    let first = *squares.iter().min()?;
+    let last = *squares.iter().max()?;
+    if (first..=last).any(|s| castled.squares[s].is_some()) {
+        return None;
+    }
+    // king cannot move across attacked squares
+    let opponent = opposite(&color);
+    let king_first = king_from.min(king_to);
+    let king_last = king_from.max(king_to);
+    if (king_first..=king_last).any(|s| castled.is_attacked(s, &opponent)) {
+        return None;
+    }
+    castled.squares[king_to] = Some(king);
+    castled.squares[rook_to] = Some(rook);
+    castled.en_passant = None;
+    castled.turn_color = opponent;
+    Some(castled)
+}
+
+fn empty_files(rank: &[char; 8]) -> Vec<usize> {
+    (0..8).filter(|file| rank[*file] == ' ').collect()
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use crate::chess960::{back_rank, castle, start_fen, update_castling, CastlingSide};
+    use crate::cwchess::CwChessGameOver;
+    use crate::error::ContractError;
+
+    #[test]
+    fn test_back_rank() {
+        let rank = |position| back_rank(position).iter().collect::<String>();
+        assert_eq!(rank(0), "BBQNNRKR");
+        // standard chess
+        assert_eq!(rank(518), "RNBQKBNR");
+        assert_eq!(rank(959), "RKRNNQBB");
+    }
+
+    #[test]
+    fn test_start_fen() {
+        assert_eq!(
+            start_fen(518),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+        assert_eq!(
+            start_fen(0),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_castle() {
+        let fen = "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1";
+        // king side, rook already next to king destination
+        let (status, fen) = castle(fen, &CastlingSide::King).unwrap();
+        assert_eq!(status, None);
+        assert_eq!(fen, "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b gb - 0 1");
+        // queen side
+        let (_, fen) = castle(&fen, &CastlingSide::Queen).unwrap();
+        assert_eq!(fen, "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 0 1");
+        // no rights left
+        match castle(&fen, &CastlingSide::Queen).unwrap_err() {
+            ContractError::InvalidMove { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_castle_blocked() {
+        // knight between king and rook
+        let fen = "4k3/8/8/8/8/8/8/RN2K3 w A - 0 1";
+        assert!(castle(fen, &CastlingSide::Queen).is_err());
+        // king would cross attacked square
+        let fen = "3rk3/8/8/8/8/8/8/R3K3 w A - 0 1";
+        assert!(castle(fen, &CastlingSide::Queen).is_err());
+        // king in check
+        let fen = "4r1k1/8/8/8/8/8/8/R3K3 w A - 0 1";
+        assert!(castle(fen, &CastlingSide::Queen).is_err());
+        // rook may be attacked
+        let fen = "r3k3/8/8/8/8/8/8/R3K3 w A - 0 1";
+        assert!(castle(fen, &CastlingSide::Queen).is_ok());
+    }
+
+    #[test]
+    fn test_castle_checkmate() {
+        // rook lands on d1 with check
+        let fen = "2rk4/2p1p3/8/8/8/8/8/R3K3 w A - 0 1";
+        let (status, _) = castle(fen, &CastlingSide::Queen).unwrap();
+        assert_eq!(status, None);
+        // king has no escape
+        let fen = "2rkr3/2p1p3/8/8/8/8/8/R3K3 w A - 0 1";
+        let (status, _) = castle(fen, &CastlingSide::Queen).unwrap();
+        assert_eq!(status, Some(CwChessGameOver::WhiteCheckmates));
+    }
+
+    #[test]
+    fn test_update_castling() {
+        let fen = "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1";
+        // rook move removes right for that rook
+        assert_eq!(
+            update_castling(fen, "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/R3K1R1 b - - 0 1"),
+            "Ggb"
+        );
+        // king move removes both rights
+        assert_eq!(
+            update_castling(fen, "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 b - - 0 1"),
+            "gb"
+        );
+        // captured rook
+        assert_eq!(
+            update_castling(
+                "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1",
+                "1R2k1r1/8/8/8/8/8/8/4K1R1 b - - 0 1"
+            ),
+            "Gg"
+        );
+    }
+}
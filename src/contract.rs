@@ -1,18 +1,32 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    from_binary, to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order,
+    Response, StdResult, Storage,
 };
 use cw2::set_contract_version;
+use cw20::{Cw20CoinVerified, Cw20ReceiveMsg};
 use cw_storage_plus::Bound;
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame};
+use crate::board::opposite;
+use crate::chess960;
+use crate::cwchess::{
+    CwChessAction, CwChessCategory, CwChessColor, CwChessColorAssignment, CwChessGame,
+    CwChessGameOver, CwChessTimeControl, CwChessVariant,
+};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg};
+use crate::fen;
+use crate::msg::{
+    CreateChallengeMsg, ExecuteMsg, GameClock, GameSummary, InstantiateMsg, LeaderboardCursor,
+    LeaderboardPage, PlayerSummary, QueryMsg, RatingSummary, ReceiveMsg, SeekMsg,
+};
+use crate::rating;
 use crate::state::{
-    get_challenges_map, get_games_map, merge_iters, next_challenge_id, next_game_id, Challenge,
-    State, STATE,
+    get_challenges_map, get_games_map, get_player_ratings_map, get_seeks_map, load_player_rating,
+    merge_iters, next_challenge_id, next_game_id, Challenge, PlayerStats, Seek, State,
+    PLAYER_STATS, STATE,
 };
+use crate::wager::Wager;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cosmwasm-chess";
@@ -24,10 +38,19 @@ pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    let cw20_tokens = msg
+        .cw20_tokens
+        .unwrap_or_default()
+        .iter()
+        .map(|token| deps.api.addr_validate(token))
+        .collect::<StdResult<Vec<_>>>()?;
     let state = State {
+        cw20_tokens,
+        inactivity_blocks: msg.inactivity_blocks,
         owner: info.sender.clone(),
+        rating_system: msg.rating_system.unwrap_or_default(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
@@ -46,48 +69,82 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::AcceptChallenge { challenge_id } => {
-            execute_accept_challenge(deps, env, info, challenge_id)
+            let wager = Wager::from_funds(&info.funds)?;
+            execute_accept_challenge(deps, env, info.sender, wager, challenge_id)
         }
         ExecuteMsg::CancelChallenge { challenge_id } => {
             execute_cancel_challenge(deps, info, challenge_id)
         }
-        ExecuteMsg::CreateChallenge {
-            block_limit,
-            opponent,
-            play_as,
-        } => execute_create_challenge(deps, env, info, block_limit, opponent, play_as),
+        ExecuteMsg::CreateChallenge(msg) => {
+            let wager = Wager::from_funds(&info.funds)?;
+            execute_create_challenge(deps, env, info.sender, wager, msg)
+        }
+        ExecuteMsg::ClaimAbandonment { game_id } => {
+            execute_claim_abandonment(deps, env, info, game_id)
+        }
+        ExecuteMsg::DeclineChallenge { challenge_id } => {
+            execute_decline_challenge(deps, info, challenge_id)
+        }
         ExecuteMsg::DeclareTimeout { game_id } => execute_declare_timeout(deps, env, game_id),
+        ExecuteMsg::PruneChallenges { limit } => execute_prune_challenges(deps, env, limit),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::Rematch { game_id } => {
+            let wager = Wager::from_funds(&info.funds)?;
+            execute_rematch(deps, env, info.sender, wager, game_id)
+        }
+        ExecuteMsg::Seek(msg) => {
+            let wager = Wager::from_funds(&info.funds)?;
+            execute_seek(deps, env, info.sender, wager, msg)
+        }
         ExecuteMsg::Turn { action, game_id } => execute_turn(deps, env, info, action, game_id),
+        ExecuteMsg::WithdrawSeek { seek_id } => execute_withdraw_seek(deps, info, seek_id),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
+        QueryMsg::GetClock { game_id } => to_binary(&query_get_clock(deps, env, game_id)?),
         QueryMsg::GetGame { game_id } => to_binary(&query_get_game(deps, game_id)?),
         QueryMsg::GetChallenge { challenge_id } => {
             to_binary(&query_get_challenge(deps, challenge_id)?)
         }
-        QueryMsg::GetChallenges { after, player } => {
-            to_binary(&query_get_challenges(deps, after, player)?)
-        }
+        QueryMsg::GetChallenges {
+            after,
+            expired,
+            player,
+        } => to_binary(&query_get_challenges(deps, env, after, expired, player)?),
         QueryMsg::GetGames {
             after,
             game_over,
             player,
         } => to_binary(&query_get_games(deps, after, game_over, player)?),
+        QueryMsg::GetLeaderboard {
+            category,
+            established,
+            start_after,
+            limit,
+        } => to_binary(&query_get_leaderboard(
+            deps,
+            category,
+            established,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::GetPlayer { address } => to_binary(&query_get_player(deps, address)?),
+        QueryMsg::GetPlayerStats { address } => to_binary(&query_get_player_stats(deps, address)?),
+        QueryMsg::GetSeeks { after } => to_binary(&query_get_seeks(deps, after)?),
     }
 }
 
 fn execute_accept_challenge(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    player: Addr,
+    wager: Option<Wager>,
     challenge_id: u64,
 ) -> Result<Response, ContractError> {
-    let block_start = env.block.height;
     let challenges_map = get_challenges_map();
-    let player = info.sender;
     // find challenge
     let challenge = match challenges_map.load(deps.storage, challenge_id) {
         Ok(challenge) => {
@@ -99,42 +156,27 @@ fn execute_accept_challenge(
                     return Err(ContractError::NotYourChallenge {});
                 }
             }
+            if challenge.is_expired(&env.block) {
+                return Err(ContractError::ChallengeExpired {});
+            }
+            if challenge.wager != wager {
+                return Err(ContractError::WagerMismatch {});
+            }
             challenge
         }
         _ => {
             return Err(ContractError::ChallengeNotFound {});
         }
     };
-    // create game
-    let game_id = next_game_id(deps.storage)?;
-    let (player1, player2) = CwChessGame::get_player_order(
-        challenge.created_by.clone(),
-        player,
-        challenge.play_as,
-        block_start,
-    );
-    // create game
-    let game = CwChessGame {
-        block_limit: challenge.block_limit,
-        block_start,
-        fen: DEFAULT_FEN.to_string(),
-        game_id,
-        player1: player1.clone(),
-        player2: player2.clone(),
-        moves: vec![],
-        status: None,
-    };
-    // update storage
-    let games_map = get_games_map();
-    games_map.save(deps.storage, game_id, &game)?;
     challenges_map.remove(deps.storage, challenge_id)?;
+    let game = start_game(deps.storage, &env, challenge, player)?;
 
     Ok(Response::new()
         .add_attribute("action", "accept_challenge")
         .add_attribute("challenge_id", challenge_id.to_string())
-        .add_attribute("game_id", game_id.to_string())
-        .add_attribute("player1", player1)
-        .add_attribute("player2", player2))
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("player1", game.player1)
+        .add_attribute("player2", game.player2))
 }
 
 fn execute_cancel_challenge(
@@ -156,24 +198,74 @@ fn execute_cancel_challenge(
         }
     };
     challenges_map.remove(deps.storage, challenge.challenge_id)?;
+    // refund wager
+    let refund = match challenge.wager {
+        Some(wager) => Some(wager.send_msg(&player, wager.amount())?),
+        None => None,
+    };
 
     Ok(Response::new()
+        .add_messages(refund)
         .add_attribute("action", "cancel_challenge")
         .add_attribute("challenge_id", challenge_id.to_string()))
 }
 
-fn execute_create_challenge(
+fn execute_claim_abandonment(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    block_limit: Option<u64>,
-    opponent: Option<String>,
-    play_as: Option<CwChessColor>,
+    game_id: u64,
+) -> Result<Response, ContractError> {
+    let inactivity_blocks = match STATE.load(deps.storage)?.inactivity_blocks {
+        Some(inactivity_blocks) => inactivity_blocks,
+        None => return Err(ContractError::GameNotAbandoned {}),
+    };
+    let games_map = get_games_map();
+    let player = info.sender;
+    let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+        match game {
+            None => Err(ContractError::GameNotFound {}),
+            Some(mut game) => {
+                game.claim_abandonment(&player, &env.block, inactivity_blocks)?;
+                Ok(game)
+            }
+        }
+    })?;
+
+    update_ratings(deps.storage, &env, &game)?;
+    update_stats(deps.storage, &game)?;
+
+    Ok(Response::new()
+        .add_messages(get_payout_messages(&game)?)
+        .add_attribute("action", "claim_abandonment")
+        .add_attribute("game_id", game.game_id.to_string())
+        .add_attribute("status", format!("{:?}", game.status)))
+}
+
+fn execute_create_challenge(
+    deps: DepsMut,
+    env: Env,
+    created_by: Addr,
+    wager: Option<Wager>,
+    msg: CreateChallengeMsg,
 ) -> Result<Response, ContractError> {
     let block_created = env.block.height;
+    msg.get_time_control().validate()?;
+    let variant = msg.variant.unwrap_or_default();
+    if let Some(start_fen) = &msg.start_fen {
+        // Chess960 starting position is chosen when challenge is accepted
+        if variant == CwChessVariant::Chess960 {
+            return Err(ContractError::InvalidPosition {});
+        }
+        CwChessGame::validate_fen(start_fen)?;
+    }
+    if let Some(expires_at) = &msg.expires_at {
+        if expires_at.is_expired(&env.block) {
+            return Err(ContractError::ChallengeExpired {});
+        }
+    }
     let challenge_id = next_challenge_id(deps.storage)?;
-    let created_by = info.sender;
-    let opponent = match opponent {
+    let opponent = match msg.opponent {
         Some(addr) => {
             let addr = deps.api.addr_validate(&addr)?;
             if created_by == addr {
@@ -185,11 +277,22 @@ fn execute_create_challenge(
     };
     let challenge = Challenge {
         block_created,
-        block_limit,
+        block_limit: msg.block_limit,
         challenge_id,
+        clock: msg.clock.clone().unwrap_or_default(),
         created_by: created_by.clone(),
+        delay_blocks: msg.delay_blocks,
+        expires_at: msg.expires_at,
+        first_move_limit: msg.first_move_limit,
+        increment_blocks: msg.increment_blocks,
+        move_limit: msg.move_limit,
         opponent: opponent.clone(),
-        play_as,
+        play_as: msg.play_as,
+        rated: msg.rated.unwrap_or(false),
+        rematch_of: None,
+        start_fen: msg.start_fen,
+        variant,
+        wager: wager.clone(),
     };
     let challenges_map = get_challenges_map();
     challenges_map.save(deps.storage, challenge_id, &challenge)?;
@@ -201,7 +304,21 @@ fn execute_create_challenge(
         .add_attribute(
             "opponent",
             opponent.unwrap_or_else(|| Addr::unchecked("none")),
-        ))
+        )
+        .add_attribute(
+            "wager",
+            wager
+                .map(|w| w.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+        )
+        .add_attribute("block_limit", optional_attribute(msg.block_limit))
+        .add_attribute("increment_blocks", optional_attribute(msg.increment_blocks))
+        .add_attribute("delay_blocks", optional_attribute(msg.delay_blocks))
+        .add_attribute("clock", format!("{:?}", msg.clock.unwrap_or_default()))
+        .add_attribute("move_limit", optional_attribute(msg.move_limit))
+        .add_attribute("first_move_limit", optional_attribute(msg.first_move_limit))
+        .add_attribute("expires_at", optional_attribute(msg.expires_at))
+        .add_attribute("rated", challenge.rated.to_string()))
 }
 
 fn execute_declare_timeout(
@@ -210,22 +327,293 @@ fn execute_declare_timeout(
     game_id: u64,
 ) -> Result<Response, ContractError> {
     let games_map = get_games_map();
-    let height = env.block.height;
     let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
         match game {
             None => Err(ContractError::GameNotFound {}),
-            Some(mut game) => match game.check_timeout(height)? {
+            Some(mut game) => match game.check_timeout(&env.block)? {
                 None => Err(ContractError::GameNotTimedOut {}),
                 _ => Ok(game),
             },
         }
     })?;
 
+    update_ratings(deps.storage, &env, &game)?;
+    update_stats(deps.storage, &game)?;
+
     Ok(Response::new()
+        .add_messages(get_payout_messages(&game)?)
         .add_attribute("action", "declare_timeout")
         .add_attribute("game_id", game.game_id.to_string()))
 }
 
+fn execute_decline_challenge(
+    deps: DepsMut,
+    info: MessageInfo,
+    challenge_id: u64,
+) -> Result<Response, ContractError> {
+    let challenges_map = get_challenges_map();
+    let player = info.sender;
+    let challenge = match challenges_map.load(deps.storage, challenge_id) {
+        Ok(challenge) => {
+            // only directed challenges can be declined
+            if challenge.opponent.as_ref() != Some(&player) {
+                return Err(ContractError::NotYourChallenge {});
+            }
+            challenge
+        }
+        _ => {
+            return Err(ContractError::ChallengeNotFound {});
+        }
+    };
+    challenges_map.remove(deps.storage, challenge.challenge_id)?;
+    // refund wager
+    let refund = match challenge.wager {
+        Some(wager) => Some(wager.send_msg(&challenge.created_by, wager.amount())?),
+        None => None,
+    };
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_attribute("action", "decline_challenge")
+        .add_attribute("challenge_id", challenge_id.to_string())
+        .add_attribute("created_by", challenge.created_by)
+        .add_attribute("opponent", player))
+}
+
+fn execute_prune_challenges(
+    deps: DepsMut,
+    env: Env,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let challenges_map = get_challenges_map();
+    let limit = limit.unwrap_or(25).min(25) as usize;
+    // challenges are expired at their expiration height or time
+    let expired_height = challenges_map
+        .idx
+        .expires_at
+        .sub_prefix("height".to_string())
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive((env.block.height, u64::MAX))),
+            Order::Ascending,
+        );
+    let expired_time = challenges_map
+        .idx
+        .expires_at
+        .sub_prefix("time".to_string())
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive((env.block.time.nanos(), u64::MAX))),
+            Order::Ascending,
+        );
+    let expired = expired_height
+        .chain(expired_time)
+        .take(limit)
+        .map(|result| -> StdResult<Challenge> { Ok(result?.1) })
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut refunds = vec![];
+    for challenge in expired.iter() {
+        challenges_map.remove(deps.storage, challenge.challenge_id)?;
+        // refund wager
+        if let Some(wager) = &challenge.wager {
+            refunds.push(wager.send_msg(&challenge.created_by, wager.amount())?);
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(refunds)
+        .add_attribute("action", "prune_challenges")
+        .add_attribute(
+            "challenge_ids",
+            expired
+                .iter()
+                .map(|c| c.challenge_id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        ))
+}
+
+fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // sender is token contract, cw20 sender is player
+    // any contract can send this message, so only trust listed tokens to set the player
+    if !STATE.load(deps.storage)?.cw20_tokens.contains(&info.sender) {
+        return Err(ContractError::InvalidWager {});
+    }
+    let player = deps.api.addr_validate(&msg.sender)?;
+    let wager = Some(Wager::Cw20(Cw20CoinVerified {
+        address: info.sender,
+        amount: msg.amount,
+    }));
+    match from_binary(&msg.msg)? {
+        ReceiveMsg::AcceptChallenge { challenge_id } => {
+            execute_accept_challenge(deps, env, player, wager, challenge_id)
+        }
+        ReceiveMsg::CreateChallenge(msg) => execute_create_challenge(deps, env, player, wager, msg),
+        ReceiveMsg::Rematch { game_id } => execute_rematch(deps, env, player, wager, game_id),
+        ReceiveMsg::Seek(msg) => execute_seek(deps, env, player, wager, msg),
+    }
+}
+
+fn execute_rematch(
+    deps: DepsMut,
+    env: Env,
+    player: Addr,
+    wager: Option<Wager>,
+    game_id: u64,
+) -> Result<Response, ContractError> {
+    let game = match get_games_map().may_load(deps.storage, game_id)? {
+        Some(game) => game,
+        None => return Err(ContractError::GameNotFound {}),
+    };
+    let color = match game.get_player_color(&player) {
+        Some(color) => color,
+        None => return Err(ContractError::Unauthorized {}),
+    };
+    if game.status.is_none() {
+        return Err(ContractError::GameNotOver {});
+    }
+    if game.rematched_by.is_some() {
+        return Err(ContractError::RematchRequested {});
+    }
+    let opponent = game.get_player(&opposite(&color)).clone();
+    let challenges_map = get_challenges_map();
+    let find_rematch = |created_by: &Addr| -> StdResult<Option<Challenge>> {
+        challenges_map
+            .idx
+            .created_by
+            .prefix(created_by.clone())
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|result| -> StdResult<Challenge> { Ok(result?.1) })
+            .find(|result| -> bool {
+                match result {
+                    Ok(c) => c.rematch_of == Some(game_id),
+                    Err(_) => true,
+                }
+            })
+            .transpose()
+    };
+    if find_rematch(&player)?.is_some() {
+        return Err(ContractError::RematchRequested {});
+    }
+
+    // both players requested rematch
+    if let Some(challenge) = find_rematch(&opponent)? {
+        if challenge.wager != wager {
+            return Err(ContractError::WagerMismatch {});
+        }
+        let challenge_id = challenge.challenge_id;
+        challenges_map.remove(deps.storage, challenge_id)?;
+        let game = start_game(deps.storage, &env, challenge, player)?;
+
+        return Ok(Response::new()
+            .add_attribute("action", "rematch")
+            .add_attribute("challenge_id", challenge_id.to_string())
+            .add_attribute("game_id", game.game_id.to_string())
+            .add_attribute("player1", game.player1)
+            .add_attribute("player2", game.player2));
+    }
+
+    let challenge_id = next_challenge_id(deps.storage)?;
+    let challenge = Challenge {
+        block_created: env.block.height,
+        block_limit: game.block_limit,
+        challenge_id,
+        clock: game.clock,
+        created_by: player,
+        delay_blocks: game.delay_blocks,
+        expires_at: None,
+        first_move_limit: game.first_move_limit,
+        increment_blocks: game.increment_blocks,
+        move_limit: game.move_limit,
+        play_as: Some(opposite(&color)),
+        opponent: Some(opponent.clone()),
+        rated: game.rated,
+        rematch_of: Some(game_id),
+        // Chess960 starting position is chosen again
+        start_fen: match game.variant {
+            CwChessVariant::Chess960 => None,
+            CwChessVariant::Standard => game.start_fen,
+        },
+        variant: game.variant,
+        wager,
+    };
+    challenges_map.save(deps.storage, challenge_id, &challenge)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "rematch")
+        .add_attribute("challenge_id", challenge_id.to_string())
+        .add_attribute("created_by", challenge.created_by)
+        .add_attribute("opponent", opponent))
+}
+
+fn execute_seek(
+    deps: DepsMut,
+    env: Env,
+    player: Addr,
+    wager: Option<Wager>,
+    msg: SeekMsg,
+) -> Result<Response, ContractError> {
+    msg.time_control.validate()?;
+    let category = CwChessCategory::from(&msg.time_control);
+    let rating = load_player_rating(deps.storage, &player, &category)?.rating;
+    let mut seek = Seek {
+        block_created: env.block.height,
+        created_by: player,
+        play_as: msg.play_as,
+        rated: msg.rated,
+        rating,
+        rating_range: msg.rating_range,
+        seek_id: 0,
+        time_control: msg.time_control,
+        wager,
+    };
+    let seeks_map = get_seeks_map();
+    // oldest compatible seek, from seeks with the same pairing key
+    let matched = seeks_map
+        .idx
+        .pairing
+        .prefix(seek.pairing_key())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|result| -> StdResult<Seek> { Ok(result?.1) })
+        .find(|result| -> bool {
+            match result {
+                Ok(s) => s.is_compatible(&seek),
+                Err(_) => true,
+            }
+        })
+        .transpose()?;
+    match matched {
+        None => {
+            seek.seek_id = next_challenge_id(deps.storage)?;
+            seeks_map.save(deps.storage, seek.seek_id, &seek)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "seek")
+                .add_attribute("seek_id", seek.seek_id.to_string())
+                .add_attribute("created_by", seek.created_by))
+        }
+        Some(matched) => {
+            seeks_map.remove(deps.storage, matched.seek_id)?;
+            let challenge = matched.to_challenge(&seek);
+            let game = start_game(deps.storage, &env, challenge, seek.created_by)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "seek")
+                .add_attribute("seek_id", matched.seek_id.to_string())
+                .add_attribute("game_id", game.game_id.to_string())
+                .add_attribute("player1", game.player1)
+                .add_attribute("player2", game.player2))
+        }
+    }
+}
+
 fn execute_turn(
     deps: DepsMut,
     env: Env,
@@ -234,19 +622,22 @@ fn execute_turn(
     game_id: u64,
 ) -> Result<Response, ContractError> {
     let games_map = get_games_map();
-    let height = env.block.height;
     let player = info.sender;
     let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
         match game {
             None => Err(ContractError::GameNotFound {}),
             Some(mut game) => {
-                game.make_move(&player, (height, action.clone()))?;
+                game.make_move(&player, &env.block, action.clone())?;
                 Ok(game)
             }
         }
     })?;
 
+    update_ratings(deps.storage, &env, &game)?;
+    update_stats(deps.storage, &game)?;
+
     Ok(Response::new()
+        .add_messages(get_payout_messages(&game)?)
         .add_attribute("action", "turn")
         .add_attribute("game_id", game.game_id.to_string())
         .add_attribute(
@@ -258,6 +649,188 @@ fn execute_turn(
         ))
 }
 
+fn execute_withdraw_seek(
+    deps: DepsMut,
+    info: MessageInfo,
+    seek_id: u64,
+) -> Result<Response, ContractError> {
+    let seeks_map = get_seeks_map();
+    let player = info.sender;
+    let seek = match seeks_map.load(deps.storage, seek_id) {
+        Ok(seek) => {
+            if seek.created_by != player {
+                return Err(ContractError::Unauthorized {});
+            }
+            seek
+        }
+        _ => {
+            return Err(ContractError::SeekNotFound {});
+        }
+    };
+    seeks_map.remove(deps.storage, seek_id)?;
+    // refund wager
+    let refund = match seek.wager {
+        Some(wager) => Some(wager.send_msg(&player, wager.amount())?),
+        None => None,
+    };
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_attribute("action", "withdraw_seek")
+        .add_attribute("seek_id", seek_id.to_string()))
+}
+
+// create game from challenge accepted by player
+fn start_game(
+    storage: &mut dyn Storage,
+    env: &Env,
+    challenge: Challenge,
+    player: Addr,
+) -> Result<CwChessGame, ContractError> {
+    let challenge_id = challenge.challenge_id;
+    let game_id = next_game_id(storage)?;
+    let hash = CwChessGame::get_hash(challenge_id, &env.block, &challenge.created_by, &player);
+    let color_assignment = match challenge.play_as {
+        Some(_) => CwChessColorAssignment::PlayAs,
+        None => CwChessColorAssignment::Hash {
+            challenge_id,
+            block_time: env.block.time,
+            hash: hash.clone(),
+        },
+    };
+    let (player1, player2) = CwChessGame::get_player_order(
+        challenge.created_by.clone(),
+        player,
+        challenge.play_as,
+        &hash,
+    );
+    let chess960_position = match challenge.variant {
+        CwChessVariant::Chess960 => Some(CwChessGame::get_chess960_position(&hash)),
+        CwChessVariant::Standard => None,
+    };
+    let start_fen = match chess960_position {
+        Some(position) => Some(chess960::start_fen(position)),
+        None => challenge.start_fen,
+    };
+    let fen = start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string());
+    let category = CwChessCategory::from(&CwChessTimeControl {
+        block_limit: challenge.block_limit,
+        clock: challenge.clock.clone(),
+        delay_blocks: challenge.delay_blocks,
+        first_move_limit: challenge.first_move_limit,
+        increment_blocks: challenge.increment_blocks,
+        move_limit: challenge.move_limit,
+    });
+    let game = CwChessGame {
+        block_limit: challenge.block_limit,
+        block_start: env.block.height,
+        category: Some(category),
+        chess960_position,
+        clock: challenge.clock,
+        color_assignment: Some(color_assignment),
+        delay_blocks: challenge.delay_blocks,
+        draw_offer: None,
+        first_move_limit: challenge.first_move_limit,
+        game_id,
+        increment_blocks: challenge.increment_blocks,
+        move_limit: challenge.move_limit,
+        player1,
+        player2,
+        move_times: vec![],
+        moves: vec![],
+        positions: vec![fen::position_key(&fen)],
+        rated: challenge.rated,
+        rematch_of: challenge.rematch_of,
+        rematched_by: None,
+        fen,
+        final_action: None,
+        start_fen,
+        status: None,
+        takeback_height: None,
+        takeback_request: None,
+        takeback_time: None,
+        takeback_used_times: (0, 0),
+        time_start: env.block.time.seconds(),
+        undo_fens: vec![],
+        variant: challenge.variant,
+        wager: challenge.wager,
+    };
+    let games_map = get_games_map();
+    games_map.save(storage, game_id, &game)?;
+    // link previous game to its rematch
+    if let Some(rematch_of) = game.rematch_of {
+        let mut previous = games_map.load(storage, rematch_of)?;
+        previous.rematched_by = Some(game_id);
+        games_map.save(storage, rematch_of, &previous)?;
+    }
+    Ok(game)
+}
+
+// send both wagers to winner, or refund each player for a draw
+fn get_payout_messages(game: &CwChessGame) -> StdResult<Vec<CosmosMsg>> {
+    let (status, wager) = match (&game.status, &game.wager) {
+        (Some(status), Some(wager)) => (status, wager),
+        _ => return Ok(vec![]),
+    };
+    match status.winner() {
+        Some(color) => {
+            Ok(vec![wager.send_msg(
+                game.get_player(&color),
+                wager.amount() + wager.amount(),
+            )?])
+        }
+        None => vec![&game.player1, &game.player2]
+            .into_iter()
+            .map(|player| wager.send_msg(player, wager.amount()))
+            .collect(),
+    }
+}
+
+// update player ratings when rated game ends
+fn update_ratings(storage: &mut dyn Storage, env: &Env, game: &CwChessGame) -> StdResult<()> {
+    let status = match &game.status {
+        Some(status) if game.rated && *status != CwChessGameOver::Aborted => status,
+        _ => return Ok(()),
+    };
+    let category = game.get_category();
+    let mut white = load_player_rating(storage, &game.player1, &category)?;
+    let mut black = load_player_rating(storage, &game.player2, &category)?;
+    let rating_system = STATE.load(storage)?.rating_system;
+    rating::update_ratings(
+        &mut white,
+        &mut black,
+        status,
+        &rating_system,
+        env.block.height,
+    );
+    let player_ratings_map = get_player_ratings_map();
+    player_ratings_map.save(storage, (&game.player1, category.as_str()), &white)?;
+    player_ratings_map.save(storage, (&game.player2, category.as_str()), &black)?;
+    Ok(())
+}
+
+// add result to each player's stats when game ends
+fn update_stats(storage: &mut dyn Storage, game: &CwChessGame) -> StdResult<()> {
+    let status = match &game.status {
+        Some(status) => status,
+        None => return Ok(()),
+    };
+    for color in [CwChessColor::White, CwChessColor::Black] {
+        let player = game.get_player(&color);
+        let mut stats = PLAYER_STATS.may_load(storage, player)?.unwrap_or_default();
+        stats.add(status, &color);
+        PLAYER_STATS.save(storage, player, &stats)?;
+    }
+    Ok(())
+}
+
+// attribute value for optional challenge settings
+fn optional_attribute<T: ToString>(value: Option<T>) -> String {
+    value
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "none".to_string())
+}
+
 fn query_get_challenge(deps: Deps, challenge_id: u64) -> StdResult<Challenge> {
     let challenges_map = get_challenges_map();
     let challenge = challenges_map.load(deps.storage, challenge_id)?;
@@ -265,6 +838,13 @@ fn query_get_challenge(deps: Deps, challenge_id: u64) -> StdResult<Challenge> {
     Ok(challenge)
 }
 
+fn query_get_clock(deps: Deps, env: Env, game_id: u64) -> StdResult<GameClock> {
+    let games_map = get_games_map();
+    let game = games_map.load(deps.storage, game_id)?;
+
+    Ok(GameClock::new(&game, &env.block))
+}
+
 fn query_get_game(deps: Deps, game_id: u64) -> StdResult<CwChessGame> {
     let games_map = get_games_map();
     let game = games_map.load(deps.storage, game_id)?;
@@ -274,11 +854,14 @@ fn query_get_game(deps: Deps, game_id: u64) -> StdResult<CwChessGame> {
 
 fn query_get_challenges(
     deps: Deps,
+    env: Env,
     after: Option<u64>,
+    expired: Option<bool>,
     player: Option<String>,
 ) -> StdResult<Vec<Challenge>> {
     let challenges_map = get_challenges_map();
     let after = after.map(Bound::exclusive);
+    let expired = expired.unwrap_or(false);
 
     let challenges = match player {
         None => {
@@ -289,7 +872,10 @@ fn query_get_challenges(
                 .range(deps.storage, after, None, Order::Ascending)
                 .map(|result| -> Challenge { result.unwrap().1 });
 
-            open_challenges.take(25).collect::<Vec<_>>()
+            open_challenges
+                .filter(|c| -> bool { expired || !c.is_expired(&env.block) })
+                .take(25)
+                .collect::<Vec<_>>()
         }
         Some(addr) => {
             let addr = deps.api.addr_validate(&addr)?;
@@ -309,6 +895,7 @@ fn query_get_challenges(
             merge_iters(created_by, opponent, |c1, c2| -> bool {
                 c1.challenge_id <= c2.challenge_id
             })
+            .filter(|c| -> bool { expired || !c.is_expired(&env.block) })
             .take(25)
             .collect::<Vec<_>>()
         }
@@ -366,3 +953,90 @@ fn query_get_games(
 
     Ok(games)
 }
+
+fn query_get_leaderboard(
+    deps: Deps,
+    category: CwChessCategory,
+    established: Option<bool>,
+    start_after: Option<LeaderboardCursor>,
+    limit: Option<u32>,
+) -> StdResult<LeaderboardPage> {
+    let player_ratings_map = get_player_ratings_map();
+    let limit = limit.unwrap_or(25).min(25) as usize;
+    // ratings are in ascending order, so page ends before previous player
+    let before = match start_after {
+        Some(cursor) => {
+            let address = deps.api.addr_validate(&cursor.address)?;
+            // player must have a rating in category
+            player_ratings_map.load(deps.storage, (&address, category.as_str()))?;
+            Some(Bound::exclusive((
+                cursor.rating,
+                (address, category.as_str().to_string()),
+            )))
+        }
+        None => None,
+    };
+    let index = if established.unwrap_or(false) {
+        &player_ratings_map.idx.established_rating
+    } else {
+        &player_ratings_map.idx.rating
+    };
+
+    // one extra rating to check whether there is another page
+    let mut ratings = index
+        .sub_prefix(category.as_str().to_string())
+        .range(deps.storage, None, before, Order::Descending)
+        .map(|result| -> RatingSummary { RatingSummary::from(&result.unwrap().1) })
+        .take(limit + 1)
+        .collect::<Vec<_>>();
+    let next = if ratings.len() > limit {
+        ratings.truncate(limit);
+        ratings.last().map(|last| -> LeaderboardCursor {
+            LeaderboardCursor {
+                address: last.address.clone(),
+                rating: last.rating,
+            }
+        })
+    } else {
+        None
+    };
+
+    Ok(LeaderboardPage { next, ratings })
+}
+
+fn query_get_player(deps: Deps, address: String) -> StdResult<PlayerSummary> {
+    let address = deps.api.addr_validate(&address)?;
+    let ratings = CwChessCategory::ALL
+        .iter()
+        .map(|category| -> StdResult<RatingSummary> {
+            let rating = load_player_rating(deps.storage, &address, category)?;
+            Ok(RatingSummary::from(&rating))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PlayerSummary {
+        address: address.to_string(),
+        ratings,
+    })
+}
+
+fn query_get_player_stats(deps: Deps, address: String) -> StdResult<PlayerStats> {
+    let address = deps.api.addr_validate(&address)?;
+    let stats = PLAYER_STATS
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+
+    Ok(stats)
+}
+
+fn query_get_seeks(deps: Deps, after: Option<u64>) -> StdResult<Vec<Seek>> {
+    let after = after.map(Bound::exclusive);
+    let seeks_map = get_seeks_map();
+    let seeks = seeks_map
+        .range(deps.storage, after, None, Order::Ascending)
+        .map(|result| -> Seek { result.unwrap().1 })
+        .take(25)
+        .collect::<Vec<_>>();
+
+    Ok(seeks)
+}
@@ -1,20 +1,36 @@
 #[cfg(test)]
 mod tests {
+    use crate::chess960::{start_fen, CHESS960_POSITIONS};
     use crate::contract::{execute, instantiate, query};
-    use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+    use crate::cwchess::{
+        CwChessAction, CwChessCategory, CwChessClock, CwChessColor, CwChessColorAssignment,
+        CwChessGame, CwChessGameOver, CwChessTimeControl, CwChessVariant,
+    };
     use crate::error::ContractError;
-    use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg};
+    use crate::fen::position_key;
+    use crate::msg::{
+        CreateChallengeMsg, ExecuteMsg, GameClock, GameSummary, InstantiateMsg, LeaderboardCursor,
+        LeaderboardPage, PlayerSummary, QueryMsg, RatingSummary, ReceiveMsg, SeekMsg,
+    };
+    use crate::state::{
+        get_games_map, get_player_ratings_map, Challenge, PlayerRating, PlayerStats, Seek,
+    };
+    use crate::wager::Wager;
 
     use cosmwasm_std::testing::{
         mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
     };
-    use cosmwasm_std::{coins, from_binary, Env};
+    use cosmwasm_std::{
+        coins, from_binary, to_binary, Addr, BankMsg, CosmosMsg, Env, StdResult, Timestamp,
+        Uint128, WasmMsg,
+    };
+    use cw20::{Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg, Expiration};
 
     #[test]
     fn test_initialize() {
         let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg::default();
         let info = mock_info("creator", &coins(1000, "earth"));
 
         // we can just call .unwrap() to assert this was a success
@@ -31,7 +47,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
         // create challenge
@@ -39,11 +55,12 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("creator", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: None,
                 play_as: None,
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
 
@@ -65,6 +82,27 @@ mod tests {
         assert_eq!(&attr.key, "game_id");
         assert_eq!(&attr.value, "1");
 
+        // colors assigned using hash
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        let creator = Addr::unchecked("creator");
+        let other = Addr::unchecked("other");
+        let hash = CwChessGame::get_hash(1, &mock_env().block, &creator, &other);
+        assert_eq!(
+            game.color_assignment,
+            Some(CwChessColorAssignment::Hash {
+                challenge_id: 1,
+                block_time: mock_env().block.time,
+                hash: hash.clone(),
+            })
+        );
+        assert_eq!(
+            (game.player1, game.player2),
+            CwChessGame::get_player_order(creator, other, None, &hash)
+        );
+
         // not found after accepted
         let response = execute(
             deps.as_mut(),
@@ -87,7 +125,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
         // create challenge
@@ -95,11 +133,12 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("creator", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: Some("opponent".to_string()),
                 play_as: None,
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
 
@@ -134,11 +173,236 @@ mod tests {
         assert_eq!(&attr.value, "1");
     }
 
+    #[test]
+    fn test_challenge_expiration() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // cannot create challenge that already expired
+        let response = execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("creator", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                expires_at: Some(Expiration::AtHeight(100)),
+                ..CreateChallengeMsg::default()
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::ChallengeExpired { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // challenge 1 expires at height 110, with wager
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("creator", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                expires_at: Some(Expiration::AtHeight(110)),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        // challenge 2 doesn't expire
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("creator", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg::default()),
+        )
+        .unwrap();
+        // challenge 3 expires at a time before block 110
+        let mut env = block_env(100);
+        env.block.time = env.block.time.minus_seconds(60);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                expires_at: Some(Expiration::AtTime(env.block.time.plus_seconds(30))),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        // challenge 4 expires later
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("creator", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                expires_at: Some(Expiration::AtHeight(111)),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+
+        // expired challenges are not returned by default
+        let get_challenges = |deps: cosmwasm_std::Deps, expired: Option<bool>| -> Vec<u64> {
+            let challenges: Vec<Challenge> = from_binary(
+                &query(
+                    deps,
+                    block_env(110),
+                    QueryMsg::GetChallenges {
+                        after: None,
+                        expired,
+                        player: None,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            challenges.iter().map(|c| c.challenge_id).collect()
+        };
+        assert_eq!(get_challenges(deps.as_ref(), None), vec![2, 4]);
+        assert_eq!(get_challenges(deps.as_ref(), Some(true)), vec![1, 2, 3, 4]);
+
+        // cannot accept expired challenge
+        let response = execute(
+            deps.as_mut(),
+            block_env(110),
+            mock_info("opponent", &coins(100, "ujuno")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::ChallengeExpired { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // anyone can prune expired challenges, wager is refunded to creator
+        let response = execute(
+            deps.as_mut(),
+            block_env(110),
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneChallenges { limit: Some(1) },
+        )
+        .unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+        assert_eq!(&response.attributes[1].value, "1");
+        assert_eq!(get_challenges(deps.as_ref(), Some(true)), vec![2, 3, 4]);
+        // challenges expiring at a time are also pruned
+        let response = execute(
+            deps.as_mut(),
+            block_env(110),
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneChallenges { limit: None },
+        )
+        .unwrap();
+        assert!(response.messages.is_empty());
+        assert_eq!(&response.attributes[1].value, "3");
+        assert_eq!(get_challenges(deps.as_ref(), Some(true)), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_claim_abandonment() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                inactivity_blocks: Some(1000),
+                ..InstantiateMsg::default()
+            },
+        )
+        .unwrap();
+        // create game without time limit
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+        // game without moves can only be aborted
+        let response = execute(
+            deps.as_mut(),
+            block_env(5000),
+            mock_info("black", &[]),
+            ExecuteMsg::ClaimAbandonment { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::GameNotAbandoned { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            block_env(200),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::from("e4"),
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        // player to move cannot claim
+        let response = execute(
+            deps.as_mut(),
+            block_env(2000),
+            mock_info("black", &[]),
+            ExecuteMsg::ClaimAbandonment { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // black has not been inactive long enough
+        let response = execute(
+            deps.as_mut(),
+            block_env(1200),
+            mock_info("white", &[]),
+            ExecuteMsg::ClaimAbandonment { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::GameNotAbandoned { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let response = execute(
+            deps.as_mut(),
+            block_env(1201),
+            mock_info("white", &[]),
+            ExecuteMsg::ClaimAbandonment { game_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(response.attributes[2].value, "Some(BlackAbandons)");
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::BlackAbandons));
+        assert_eq!(game.status.unwrap().winner(), Some(CwChessColor::White));
+    }
+
     #[test]
     fn test_create_challenge() {
         let mut deps = mock_dependencies();
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg::default();
         let mut env = mock_env();
         env.block.height = 123;
         let info = mock_info("owner", &coins(1000, "hello"));
@@ -147,11 +411,12 @@ mod tests {
         assert_eq!(0, init_res.messages.len());
 
         // create a challenge with an opponent
-        let msg = ExecuteMsg::CreateChallenge {
+        let msg = ExecuteMsg::CreateChallenge(CreateChallengeMsg {
             block_limit: None,
             opponent: Some("opponent".to_string()),
             play_as: None,
-        };
+            ..CreateChallengeMsg::default()
+        });
         let mut env = mock_env();
         env.block.height = 456;
         let info = mock_info("creator", &[]);
@@ -163,6 +428,132 @@ mod tests {
         let attr = attrs[1].clone();
         assert_eq!(&attr.key, "challenge_id");
         assert_eq!(&attr.value, "1");
+
+        // create a challenge with increment
+        let msg = ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+            block_limit: Some(900),
+            increment_blocks: Some(5),
+            ..CreateChallengeMsg::default()
+        });
+        let execute_res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        let attrs = execute_res.unwrap().attributes;
+        let attr = attrs[5].clone();
+        assert_eq!(&attr.key, "block_limit");
+        assert_eq!(&attr.value, "900");
+        let attr = attrs[6].clone();
+        assert_eq!(&attr.key, "increment_blocks");
+        assert_eq!(&attr.value, "5");
+        let attr = attrs[7].clone();
+        assert_eq!(&attr.key, "delay_blocks");
+        assert_eq!(&attr.value, "none");
+        // increment is included in game summary
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+        )
+        .unwrap();
+        let games = from_binary::<Vec<GameSummary>>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetGames {
+                    after: None,
+                    game_over: None,
+                    player: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(games[0].increment_blocks, Some(5));
+
+        // time limits must not be unreasonably large
+        let msg = ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+            block_limit: Some(900),
+            increment_blocks: Some(u64::MAX),
+            ..CreateChallengeMsg::default()
+        });
+        match execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg) {
+            Err(ContractError::InvalidTimeControl {}) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        let msg = ExecuteMsg::Seek(SeekMsg {
+            time_control: CwChessTimeControl {
+                block_limit: Some(u64::MAX),
+                ..CwChessTimeControl::default()
+            },
+            ..SeekMsg::default()
+        });
+        match execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg) {
+            Err(ContractError::InvalidTimeControl {}) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_decline_challenge() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create directed challenge with wager
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                opponent: Some("opponent".to_string()),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+
+        // only opponent can decline
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::DeclineChallenge { challenge_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::NotYourChallenge { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // wager refunded to creator when declined
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("opponent", &[]),
+            ExecuteMsg::DeclineChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(&response.attributes[0].value, "decline_challenge");
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("opponent", &[]),
+            ExecuteMsg::DeclineChallenge { challenge_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::ChallengeNotFound { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
     }
 
     #[test]
@@ -174,7 +565,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
         // create challenge
@@ -182,12 +573,13 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("black", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: None,
                 // creator is black
                 play_as: Some(CwChessColor::Black),
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         // opponent can accept
@@ -254,7 +646,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
 
@@ -263,11 +655,12 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("one", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: None,
                 play_as: Some(CwChessColor::Black),
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         execute(
@@ -300,12 +693,13 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("two", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: None,
                 // creator is black
                 play_as: Some(CwChessColor::Black),
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         // opponent can accept
@@ -356,6 +750,101 @@ mod tests {
         assert_eq!(games[1].player2, "two");
     }
 
+    #[test]
+    fn test_leaderboard() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // players with ratings
+        for (address, category, games, rating) in [
+            ("player1", CwChessCategory::Blitz, 30, 1700),
+            ("player2", CwChessCategory::Blitz, 5, 1600),
+            ("player3", CwChessCategory::Blitz, 25, 1800),
+            ("player4", CwChessCategory::Rapid, 40, 1900),
+        ] {
+            let address = Addr::unchecked(address);
+            get_player_ratings_map()
+                .save(
+                    deps.as_mut().storage,
+                    (&address, category.as_str()),
+                    &PlayerRating {
+                        games,
+                        rating,
+                        ..PlayerRating::new(address.clone(), category.clone())
+                    },
+                )
+                .unwrap();
+        }
+        let get_leaderboard = |deps: cosmwasm_std::Deps,
+                               established: Option<bool>,
+                               start_after: Option<LeaderboardCursor>,
+                               limit: Option<u32>|
+         -> StdResult<LeaderboardPage> {
+            from_binary(&query(
+                deps,
+                mock_env(),
+                QueryMsg::GetLeaderboard {
+                    category: CwChessCategory::Blitz,
+                    established,
+                    start_after,
+                    limit,
+                },
+            )?)
+        };
+        let addresses = |page: &LeaderboardPage| -> Vec<String> {
+            page.ratings.iter().map(|r| r.address.clone()).collect()
+        };
+
+        // highest rating first, only players in category
+        let page = get_leaderboard(deps.as_ref(), None, None, None).unwrap();
+        assert_eq!(addresses(&page), vec!["player3", "player1", "player2"]);
+        assert_eq!(page.next, None);
+        // provisional players can be left out
+        let page = get_leaderboard(deps.as_ref(), Some(true), None, None).unwrap();
+        assert_eq!(addresses(&page), vec!["player3", "player1"]);
+        // paging
+        let page = get_leaderboard(deps.as_ref(), None, None, Some(1)).unwrap();
+        assert_eq!(addresses(&page), vec!["player3"]);
+        assert_eq!(
+            page.next,
+            Some(LeaderboardCursor {
+                address: "player3".to_string(),
+                rating: 1800,
+            })
+        );
+        // cursor does not depend on player's current rating
+        let player3 = Addr::unchecked("player3");
+        get_player_ratings_map()
+            .save(
+                deps.as_mut().storage,
+                (&player3, CwChessCategory::Blitz.as_str()),
+                &PlayerRating {
+                    games: 26,
+                    rating: 1650,
+                    ..PlayerRating::new(player3.clone(), CwChessCategory::Blitz)
+                },
+            )
+            .unwrap();
+        let page = get_leaderboard(deps.as_ref(), None, page.next, Some(1)).unwrap();
+        assert_eq!(addresses(&page), vec!["player1"]);
+        let page = get_leaderboard(deps.as_ref(), None, page.next, None).unwrap();
+        assert_eq!(addresses(&page), vec!["player3", "player2"]);
+        assert_eq!(page.next, None);
+        // start_after must be a player in category
+        let unknown = LeaderboardCursor {
+            address: "player4".to_string(),
+            rating: 1900,
+        };
+        assert!(get_leaderboard(deps.as_ref(), None, Some(unknown), None).is_err());
+    }
+
     #[test]
     fn test_make_move() {
         let mut deps = mock_dependencies();
@@ -365,7 +854,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
         // create challenge
@@ -373,12 +862,13 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("black", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 block_limit: None,
                 opponent: None,
                 // creator is black
                 play_as: Some(CwChessColor::Black),
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         // opponent can accept
@@ -475,7 +965,7 @@ mod tests {
     }
 
     #[test]
-    fn test_resign() {
+    fn test_rated() {
         let mut deps = mock_dependencies();
 
         // initialize
@@ -483,20 +973,256 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
-        // create game
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("black", &[]),
-            ExecuteMsg::CreateChallenge {
-                block_limit: None,
-                opponent: None,
-                // creator is black
-                play_as: Some(CwChessColor::Black),
-            },
+        // rated and casual games
+        for rated in [true, false] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("white", &[]),
+                ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                    // 5 minute blitz game
+                    block_limit: Some(300),
+                    clock: Some(CwChessClock::Seconds),
+                    play_as: Some(CwChessColor::White),
+                    rated: Some(rated),
+                    ..CreateChallengeMsg::default()
+                }),
+            )
+            .unwrap();
+        }
+        for challenge_id in [1, 2] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("black", &[]),
+                ExecuteMsg::AcceptChallenge { challenge_id },
+            )
+            .unwrap();
+        }
+        let get_player = |deps: cosmwasm_std::Deps, address: &str| -> PlayerSummary {
+            from_binary(
+                &query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::GetPlayer {
+                        address: address.to_string(),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        let white = get_player(deps.as_ref(), "white");
+        assert_eq!(white.ratings.len(), 5);
+        assert_eq!(
+            white.ratings[1],
+            RatingSummary {
+                address: "white".to_string(),
+                category: CwChessCategory::Blitz,
+                deviation: None,
+                draws: 0,
+                games: 0,
+                losses: 0,
+                provisional: true,
+                rating: 1500,
+                wins: 0,
+            }
+        );
+        let games: Vec<GameSummary> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetGames {
+                    after: None,
+                    game_over: None,
+                    player: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(games[0].category, CwChessCategory::Blitz);
+
+        // black resigns both games, only rated game changes ratings
+        for game_id in [1, 2] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("black", &[]),
+                ExecuteMsg::Turn {
+                    action: CwChessAction::Resign {},
+                    game_id,
+                },
+            )
+            .unwrap();
+        }
+        // only blitz ratings change
+        let white = get_player(deps.as_ref(), "white");
+        assert_eq!(white.ratings[0].games, 0);
+        let blitz = &white.ratings[1];
+        assert_eq!((blitz.games, blitz.wins, blitz.rating), (1, 1, 1520));
+        let black = get_player(deps.as_ref(), "black");
+        let blitz = &black.ratings[1];
+        assert_eq!((blitz.games, blitz.losses, blitz.rating), (1, 1, 1480));
+    }
+
+    #[test]
+    fn test_rematch() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: Some(100),
+                play_as: Some(CwChessColor::White),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // cannot rematch until game over
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Rematch { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::GameNotOver { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::Resign {},
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        // rematch creates challenge to opponent with reversed colors
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Rematch { game_id: 1 },
+        )
+        .unwrap();
+        let challenge: Challenge = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetChallenge { challenge_id: 2 },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(challenge.block_limit, Some(100));
+        assert_eq!(challenge.opponent, Some(Addr::unchecked("black")));
+        assert_eq!(challenge.play_as, Some(CwChessColor::Black));
+        assert_eq!(challenge.rematch_of, Some(1));
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Rematch { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::RematchRequested { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // game starts when both players request rematch
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::Rematch { game_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(&response.attributes[2].key, "game_id");
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 2 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.player1, "black");
+        assert_eq!(game.player2, "white");
+        assert_eq!(game.block_limit, Some(100));
+        assert_eq!(game.rematch_of, Some(1));
+        assert!(query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetChallenge { challenge_id: 2 }
+        )
+        .is_err());
+        let previous = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(previous.rematched_by, Some(2));
+
+        // only one rematch of a game
+        for player in ["white", "black"] {
+            let response = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(player, &[]),
+                ExecuteMsg::Rematch { game_id: 1 },
+            );
+            match response.unwrap_err() {
+                ContractError::RematchRequested { .. } => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         execute(
@@ -536,6 +1262,22 @@ mod tests {
         .unwrap();
         assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
 
+        // player stats updated
+        let stats: PlayerStats = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPlayerStats {
+                    address: "black".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(stats.wins.resignation, 1);
+        assert_eq!(stats.as_black.wins, 1);
+        assert_eq!(stats.total.wins, 1);
+
         // cannot move after game over
         let response = execute(
             deps.as_mut(),
@@ -552,6 +1294,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_seek() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        let blitz = CwChessTimeControl {
+            block_limit: Some(300),
+            clock: CwChessClock::Seconds,
+            increment_blocks: Some(2),
+            ..CwChessTimeControl::default()
+        };
+        // first seek waits for a match
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Seek(SeekMsg {
+                play_as: Some(CwChessColor::White),
+                rated: false,
+                time_control: blitz.clone(),
+                ..SeekMsg::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(&response.attributes[1].value, "1");
+        // different time control, rated flag or color are not matched
+        for (address, msg) in [
+            (
+                "other1",
+                SeekMsg {
+                    time_control: CwChessTimeControl::default(),
+                    ..SeekMsg::default()
+                },
+            ),
+            (
+                "other2",
+                SeekMsg {
+                    rated: true,
+                    time_control: blitz.clone(),
+                    ..SeekMsg::default()
+                },
+            ),
+            (
+                "other3",
+                SeekMsg {
+                    play_as: Some(CwChessColor::White),
+                    time_control: blitz.clone(),
+                    ..SeekMsg::default()
+                },
+            ),
+        ] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(address, &[]),
+                ExecuteMsg::Seek(msg),
+            )
+            .unwrap();
+        }
+        let seeks: Vec<Seek> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetSeeks { after: None },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(seeks.len(), 4);
+
+        // compatible seek is paired with oldest seek
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::Seek(SeekMsg {
+                play_as: None,
+                rated: false,
+                time_control: blitz,
+                ..SeekMsg::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(&response.attributes[1].value, "1");
+        assert_eq!(&response.attributes[2].value, "1");
+        let game: CwChessGame = from_binary(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.player1, Addr::unchecked("white"));
+        assert_eq!(game.player2, Addr::unchecked("black"));
+        assert_eq!(game.block_limit, Some(300));
+        assert_eq!(game.clock, CwChessClock::Seconds);
+
+        // seeks can be withdrawn by seeker
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::WithdrawSeek { seek_id: 2 },
+        );
+        match response.unwrap_err() {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other1", &[]),
+            ExecuteMsg::WithdrawSeek { seek_id: 2 },
+        )
+        .unwrap();
+        let seeks: Vec<Seek> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetSeeks { after: None },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            seeks.iter().map(|s| s.seek_id).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+    }
+
     // create an env for a specific block height
     fn block_env(height: u64) -> Env {
         let mut env = mock_env();
@@ -568,7 +1444,7 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
-            InstantiateMsg {},
+            InstantiateMsg::default(),
         )
         .unwrap();
         // create game with timeout
@@ -576,13 +1452,14 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("black", &[]),
-            ExecuteMsg::CreateChallenge {
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
                 // 300 blocks/per person @ ~10 blocks/minute => ~30 minutes/person
                 block_limit: Some(300),
                 opponent: None,
                 // creator is black
                 play_as: Some(CwChessColor::Black),
-            },
+                ..CreateChallengeMsg::default()
+            }),
         )
         .unwrap();
         // game created at block 100
@@ -662,4 +1539,1009 @@ mod tests {
         assert_eq!(result.attributes[2].key, "status");
         assert_eq!(result.attributes[2].value.contains("WhiteTimeout"), true);
     }
+
+    #[test]
+    fn test_get_clock() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: Some(300),
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+        for (height, player, action) in [(300, "white", "d4"), (310, "black", "d5")] {
+            execute(
+                deps.as_mut(),
+                block_env(height),
+                mock_info(player, &[]),
+                ExecuteMsg::Turn {
+                    action: CwChessAction::from(action),
+                    game_id: 1,
+                },
+            )
+            .unwrap();
+        }
+
+        let get_clock = |height: u64| -> GameClock {
+            from_binary(
+                &query(
+                    deps.as_ref(),
+                    block_env(height),
+                    QueryMsg::GetClock { game_id: 1 },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            get_clock(500),
+            GameClock {
+                black_remaining: Some(290),
+                black_used: 10,
+                clock: CwChessClock::Blocks,
+                flagged: false,
+                game_id: 1,
+                turn_color: Some(CwChessColor::White),
+                white_remaining: Some(110),
+                white_used: 190,
+            }
+        );
+        // white has run out of time
+        let clock = get_clock(611);
+        assert_eq!(clock.white_used, 301);
+        assert_eq!(clock.white_remaining, Some(0));
+        assert!(clock.flagged);
+    }
+
+    #[test]
+    fn test_timeout_seconds() {
+        let mut deps = mock_dependencies();
+        let time_env = |seconds: u64| -> Env {
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(seconds);
+            env
+        };
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game with 10 minutes per person
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: Some(600),
+                clock: Some(CwChessClock::Seconds),
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(response.attributes[8].key, "clock");
+        assert_eq!(response.attributes[8].value, "Seconds");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // block height stays the same, only time changes
+        for (seconds, player, action) in [(1000, "white", "d4"), (1100, "black", "d5")] {
+            execute(
+                deps.as_mut(),
+                time_env(seconds),
+                mock_info(player, &[]),
+                ExecuteMsg::Turn {
+                    action: CwChessAction::from(action),
+                    game_id: 1,
+                },
+            )
+            .unwrap();
+        }
+        let response = execute(
+            deps.as_mut(),
+            time_env(1700),
+            mock_info("black", &[]),
+            ExecuteMsg::DeclareTimeout { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::GameNotTimedOut { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            time_env(1701),
+            mock_info("black", &[]),
+            ExecuteMsg::DeclareTimeout { game_id: 1 },
+        )
+        .unwrap();
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::WhiteTimeout));
+    }
+
+    #[test]
+    fn test_wager_checkmate() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create challenge with wager
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        let attr = response.attributes[4].clone();
+        assert_eq!(&attr.key, "wager");
+        assert_eq!(&attr.value, "100ujuno");
+
+        // cannot accept without matching wager
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &coins(50, "ujuno")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::WagerMismatch { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &coins(100, "ujuno")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // fool's mate
+        for (player, action) in [("white", "f3"), ("black", "e5"), ("white", "g4")] {
+            let response = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(player, &[]),
+                ExecuteMsg::Turn {
+                    action: CwChessAction::from(action),
+                    game_id: 1,
+                },
+            )
+            .unwrap();
+            // no payout while game in progress
+            assert_eq!(response.messages.len(), 0);
+        }
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::from("Qh4#"),
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        // winner receives both wagers
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "black".to_string(),
+                amount: coins(200, "ujuno"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_wager_draw() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game with wager
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &coins(100, "ujuno")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // white offers draw, black accepts
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::OfferDraw("d4".to_string()),
+                game_id: 1,
+            },
+        )
+        .unwrap();
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::AcceptDraw {},
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        // each player is refunded
+        assert_eq!(response.messages.len(), 2);
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "white".to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+        assert_eq!(
+            response.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "black".to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_wager_aborted() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game with wager and first move limit
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                first_move_limit: Some(50),
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            block_env(100),
+            mock_info("white", &coins(100, "ujuno")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        let response = execute(
+            deps.as_mut(),
+            block_env(150),
+            mock_info("black", &[]),
+            ExecuteMsg::DeclareTimeout { game_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::GameNotTimedOut { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // white didn't move, game is aborted and each player is refunded
+        let response = execute(
+            deps.as_mut(),
+            block_env(151),
+            mock_info("black", &[]),
+            ExecuteMsg::DeclareTimeout { game_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(response.messages.len(), 2);
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::Aborted));
+    }
+
+    #[test]
+    fn test_wager_cancel() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // only one coin can be wagered
+        let mut funds = coins(100, "ujuno");
+        funds.extend(coins(100, "uatom"));
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &funds),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                play_as: None,
+                ..CreateChallengeMsg::default()
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::InvalidWager { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(100, "ujuno")),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                play_as: None,
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+
+        // wager refunded when cancelled
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::CancelChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_wager_cw20() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                cw20_tokens: Some(vec!["token".to_string(), "other_token".to_string()]),
+                ..InstantiateMsg::default()
+            },
+        )
+        .unwrap();
+        // tokens not accepted at instantiate cannot create challenges for another player
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("unknown_token", &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "black".to_string(),
+                amount: Uint128::new(100),
+                msg: to_binary(&ReceiveMsg::CreateChallenge(CreateChallengeMsg::default()))
+                    .unwrap(),
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::InvalidWager { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // create challenge by sending tokens to contract
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("token", &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "black".to_string(),
+                amount: Uint128::new(100),
+                msg: to_binary(&ReceiveMsg::CreateChallenge(CreateChallengeMsg {
+                    block_limit: None,
+                    opponent: None,
+                    // creator is black
+                    play_as: Some(CwChessColor::Black),
+                    ..CreateChallengeMsg::default()
+                }))
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+        let attr = response.attributes[2].clone();
+        assert_eq!(&attr.key, "created_by");
+        assert_eq!(&attr.value, "black");
+        let attr = response.attributes[4].clone();
+        assert_eq!(&attr.key, "wager");
+        assert_eq!(&attr.value, "100token");
+
+        // cannot accept with native coins
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &coins(100, "token")),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        );
+        match response.unwrap_err() {
+            ContractError::WagerMismatch { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // cannot accept with a different token
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other_token", &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "white".to_string(),
+                amount: Uint128::new(100),
+                msg: to_binary(&ReceiveMsg::AcceptChallenge { challenge_id: 1 }).unwrap(),
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::WagerMismatch { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("token", &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "white".to_string(),
+                amount: Uint128::new(100),
+                msg: to_binary(&ReceiveMsg::AcceptChallenge { challenge_id: 1 }).unwrap(),
+            }),
+        )
+        .unwrap();
+
+        // white resigns
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::Resign {},
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        // winner receives both wagers
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "black".to_string(),
+                    amount: Uint128::new(200),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // rematch with the same token wager
+        for player in ["black", "white"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("token", &[]),
+                ExecuteMsg::Receive(Cw20ReceiveMsg {
+                    sender: player.to_string(),
+                    amount: Uint128::new(100),
+                    msg: to_binary(&ReceiveMsg::Rematch { game_id: 1 }).unwrap(),
+                }),
+            )
+            .unwrap();
+        }
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 2 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.rematch_of, Some(1));
+        assert_eq!(
+            game.wager,
+            Some(Wager::Cw20(Cw20CoinVerified {
+                address: Addr::unchecked("token"),
+                amount: Uint128::new(100),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_fivefold_repetition() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // knights return to starting position 4 times (5th occurrence)
+        let shuffle = [
+            ("white", "Nf3"),
+            ("black", "Nf6"),
+            ("white", "Ng1"),
+            ("black", "Ng8"),
+        ];
+        for i in 0..4 {
+            for (player, action) in shuffle {
+                let game = from_binary::<CwChessGame>(
+                    &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+                )
+                .unwrap();
+                assert_eq!(game.status, None);
+                // threefold repetition can be claimed after second return
+                assert_eq!(game.can_claim_draw(), i >= 2);
+                execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(player, &[]),
+                    ExecuteMsg::Turn {
+                        action: CwChessAction::from(action),
+                        game_id: 1,
+                    },
+                )
+                .unwrap();
+            }
+        }
+
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.positions.len(), 17);
+        assert_eq!(game.status, Some(CwChessGameOver::DrawDeclared));
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // create game
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: None,
+                opponent: None,
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        // knights return to starting position twice (3rd occurrence)
+        let shuffle = [
+            ("white", "Nf3"),
+            ("black", "Nf6"),
+            ("white", "Ng1"),
+            ("black", "Ng8"),
+        ];
+        for i in 0..2 {
+            for (player, action) in shuffle {
+                // cannot claim before threefold repetition
+                let response = execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(player, &[]),
+                    ExecuteMsg::Turn {
+                        action: CwChessAction::ClaimDraw,
+                        game_id: 1,
+                    },
+                );
+                match response.unwrap_err() {
+                    ContractError::CannotClaimDraw { .. } => {}
+                    e => panic!("unexpected error {}: {:?}", i, e),
+                }
+                execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(player, &[]),
+                    ExecuteMsg::Turn {
+                        action: CwChessAction::from(action),
+                        game_id: 1,
+                    },
+                )
+                .unwrap();
+            }
+        }
+
+        // only player to move can claim
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::ClaimDraw,
+                game_id: 1,
+            },
+        );
+        match response.unwrap_err() {
+            ContractError::NotYourTurn { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::ClaimDraw,
+                game_id: 1,
+            },
+        )
+        .unwrap();
+
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::DrawDeclared));
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // game where white can capture last black rook
+        let fen = "4k3/8/8/8/8/8/3r4/4K3 w - - 0 50";
+        get_games_map()
+            .save(
+                deps.as_mut().storage,
+                1,
+                &CwChessGame {
+                    block_limit: None,
+                    block_start: 0,
+                    category: None,
+                    chess960_position: None,
+                    clock: CwChessClock::Blocks,
+                    color_assignment: None,
+                    delay_blocks: None,
+                    draw_offer: None,
+                    fen: fen.to_string(),
+                    final_action: None,
+                    first_move_limit: None,
+                    game_id: 1,
+                    increment_blocks: None,
+                    move_limit: None,
+                    move_times: vec![],
+                    moves: vec![],
+                    player1: Addr::unchecked("white"),
+                    player2: Addr::unchecked("black"),
+                    positions: vec![position_key(fen)],
+                    rated: false,
+                    rematch_of: None,
+                    rematched_by: None,
+                    start_fen: Some(fen.to_string()),
+                    status: None,
+                    takeback_height: None,
+                    takeback_request: None,
+                    takeback_time: None,
+                    takeback_used_times: (0, 0),
+                    time_start: 0,
+                    undo_fens: vec![],
+                    variant: CwChessVariant::Standard,
+                    wager: None,
+                },
+            )
+            .unwrap();
+
+        // only kings remain
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::from("Kxd2"),
+                game_id: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(response.attributes[2].key, "status");
+        assert_eq!(response.attributes[2].value, "InsufficientMaterial");
+
+        let games = from_binary::<Vec<GameSummary>>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetGames {
+                    after: None,
+                    game_over: Some(true),
+                    player: Some("white".to_string()),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(games[0].status, Some(CwChessGameOver::InsufficientMaterial));
+        assert_eq!(games[0].turn_color, None);
+    }
+
+    #[test]
+    fn test_start_fen() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // cannot create challenge with invalid position
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                start_fen: Some("4k3/8/8/8/8/8/8/8 b - - 0 30".to_string()),
+                ..CreateChallengeMsg::default()
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::InvalidPosition { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // create game where black moves first
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("black", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                block_limit: Some(100),
+                // creator is black
+                play_as: Some(CwChessColor::Black),
+                start_fen: Some("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30".to_string()),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("white", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.turn_color(), Some(CwChessColor::Black));
+        // white cannot move first
+        let response = execute(
+            deps.as_mut(),
+            block_env(10),
+            mock_info("white", &[]),
+            ExecuteMsg::Turn {
+                action: CwChessAction::from("Kd2"),
+                game_id: 1,
+            },
+        );
+        match response.unwrap_err() {
+            ContractError::NotYourTurn { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        for (height, player, action) in [
+            (10, "black", "Kd8"),
+            (50, "white", "Kd2"),
+            (70, "black", "Kc7"),
+        ] {
+            execute(
+                deps.as_mut(),
+                block_env(height),
+                mock_info(player, &[]),
+                ExecuteMsg::Turn {
+                    action: CwChessAction::from(action),
+                    game_id: 1,
+                },
+            )
+            .unwrap();
+        }
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.fen, "8/2k5/8/8/8/8/3KP3/8 w - - 3 32");
+        assert_eq!(game.turn_color(), Some(CwChessColor::White));
+
+        // white has used 40 + 230 blocks
+        execute(
+            deps.as_mut(),
+            block_env(300),
+            mock_info("black", &[]),
+            ExecuteMsg::DeclareTimeout { game_id: 1 },
+        )
+        .unwrap();
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::WhiteTimeout));
+    }
+
+    #[test]
+    fn test_chess960() {
+        let mut deps = mock_dependencies();
+
+        // initialize
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        // cannot combine chess960 with start position
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("player1", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                start_fen: Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string()),
+                variant: Some(CwChessVariant::Chess960),
+                ..CreateChallengeMsg::default()
+            }),
+        );
+        match response.unwrap_err() {
+            ContractError::InvalidPosition { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("player1", &[]),
+            ExecuteMsg::CreateChallenge(CreateChallengeMsg {
+                variant: Some(CwChessVariant::Chess960),
+                ..CreateChallengeMsg::default()
+            }),
+        )
+        .unwrap();
+        // position is chosen when challenge is accepted
+        execute(
+            deps.as_mut(),
+            block_env(1000),
+            mock_info("player2", &[]),
+            ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+        )
+        .unwrap();
+
+        let game = from_binary::<CwChessGame>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(game.variant, CwChessVariant::Chess960);
+        let position = game.chess960_position.unwrap();
+        assert!(position < CHESS960_POSITIONS);
+        assert_eq!(game.fen, start_fen(position));
+        assert_eq!(game.start_fen, Some(start_fen(position)));
+
+        let summary = GameSummary::from(&game);
+        assert_eq!(summary.chess960_position, Some(position));
+        assert_eq!(summary.variant, CwChessVariant::Chess960);
+    }
 }
@@ -1,17 +1,36 @@
+use crate::board::{opposite, Board};
+use crate::chess960;
 use crate::error::ContractError;
+use crate::fen;
+use crate::wager::Wager;
 use chess_engine::{Color, Game, GameAction, GameOver};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary, BlockInfo, Timestamp};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CwChessAction {
+    // end game without result, before player has made their first move
+    Abort,
     AcceptDraw,
+    AcceptTakeback,
+    // threefold repetition or 50 move rule
+    ClaimDraw,
+    DeclineDraw,
+    DeclineTakeback,
     #[serde(rename = "move")]
     MakeMove(String),
+    // make move and offer draw
     OfferDraw(String),
+    // offer draw on either player's turn
+    OfferDrawWithoutMove,
+    // ask opponent to undo player's last move, only in casual games
+    RequestTakeback,
     Resign,
+    WithdrawDraw,
 }
 
 impl From<&str> for CwChessAction {
@@ -20,13 +39,24 @@ impl From<&str> for CwChessAction {
     }
 }
 
-impl From<&CwChessAction> for GameAction {
-    fn from(action: &CwChessAction) -> GameAction {
+impl TryFrom<&CwChessAction> for GameAction {
+    type Error = ContractError;
+
+    fn try_from(action: &CwChessAction) -> Result<GameAction, ContractError> {
         match action {
-            CwChessAction::AcceptDraw => GameAction::AcceptDraw,
-            CwChessAction::MakeMove(move_str) => GameAction::MakeMove(move_str.to_string()),
-            CwChessAction::OfferDraw(move_str) => GameAction::OfferDraw(move_str.to_string()),
-            CwChessAction::Resign => GameAction::Resign,
+            CwChessAction::MakeMove(move_str) => Ok(GameAction::MakeMove(move_str.to_string())),
+            CwChessAction::OfferDraw(move_str) => Ok(GameAction::OfferDraw(move_str.to_string())),
+            // not handled by chess_engine
+            CwChessAction::Abort
+            | CwChessAction::AcceptDraw
+            | CwChessAction::AcceptTakeback
+            | CwChessAction::ClaimDraw
+            | CwChessAction::DeclineDraw
+            | CwChessAction::DeclineTakeback
+            | CwChessAction::OfferDrawWithoutMove
+            | CwChessAction::RequestTakeback
+            | CwChessAction::Resign
+            | CwChessAction::WithdrawDraw => Err(ContractError::InvalidMove {}),
         }
     }
 }
@@ -56,6 +86,22 @@ impl From<&CwChessColor> for Color {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessVariant {
+    Standard,
+    // Fischer Random
+    Chess960,
+}
+
+// deriving with #[default] needs Rust 1.62, newer than the pinned rust-optimizer image
+#[allow(clippy::derivable_impls)]
+impl Default for CwChessVariant {
+    fn default() -> CwChessVariant {
+        CwChessVariant::Standard
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CwChessGameOver {
@@ -68,7 +114,13 @@ pub enum CwChessGameOver {
     WhiteCheckmates,
     WhiteResigns,
     // custom results
+    // game ended without result before both players moved
+    Aborted,
+    // player to move stopped playing game without time limit
+    BlackAbandons,
     BlackTimeout,
+    InsufficientMaterial,
+    WhiteAbandons,
     WhiteTimeout,
 }
 
@@ -85,48 +137,352 @@ impl From<&GameOver> for CwChessGameOver {
     }
 }
 
+impl CwChessGameOver {
+    // color that won the game, None for draws
+    pub fn winner(&self) -> Option<CwChessColor> {
+        match self {
+            CwChessGameOver::BlackCheckmates
+            | CwChessGameOver::WhiteAbandons
+            | CwChessGameOver::WhiteResigns
+            | CwChessGameOver::WhiteTimeout => Some(CwChessColor::Black),
+            CwChessGameOver::WhiteCheckmates
+            | CwChessGameOver::BlackAbandons
+            | CwChessGameOver::BlackResigns
+            | CwChessGameOver::BlackTimeout => Some(CwChessColor::White),
+            CwChessGameOver::Aborted
+            | CwChessGameOver::DrawAccepted
+            | CwChessGameOver::DrawDeclared
+            | CwChessGameOver::InsufficientMaterial
+            | CwChessGameOver::Stalemate => None,
+        }
+    }
+}
+
+// block height and action
 pub type CwChessMove = (u64, CwChessAction);
 
+// how time limits are measured
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessClock {
+    // block height
+    Blocks,
+    // block time in seconds
+    Seconds,
+}
+
+// see CwChessVariant default
+#[allow(clippy::derivable_impls)]
+impl Default for CwChessClock {
+    fn default() -> Self {
+        CwChessClock::Blocks
+    }
+}
+
+impl CwChessClock {
+    // current time for clock
+    pub fn now(&self, block: &BlockInfo) -> u64 {
+        match self {
+            CwChessClock::Blocks => block.height,
+            CwChessClock::Seconds => block.time.seconds(),
+        }
+    }
+}
+
+// time limits used to match seeks, see CreateChallengeMsg
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessTimeControl {
+    pub block_limit: Option<u64>,
+    #[serde(default)]
+    pub clock: CwChessClock,
+    pub delay_blocks: Option<u64>,
+    pub first_move_limit: Option<u64>,
+    pub increment_blocks: Option<u64>,
+    pub move_limit: Option<u64>,
+}
+
+// largest value accepted for any time control setting, one year in seconds
+const MAX_TIME_LIMIT: u64 = 31_536_000;
+
+impl CwChessTimeControl {
+    // check that limits are small enough for clock calculations
+    pub fn validate(&self) -> Result<(), ContractError> {
+        let limits = [
+            self.block_limit,
+            self.delay_blocks,
+            self.first_move_limit,
+            self.increment_blocks,
+            self.move_limit,
+        ];
+        if limits.iter().flatten().any(|limit| *limit > MAX_TIME_LIMIT) {
+            return Err(ContractError::InvalidTimeControl {});
+        }
+        Ok(())
+    }
+}
+
+// estimated seconds per block, to classify games using a block clock
+const SECONDS_PER_BLOCK: u64 = 6;
+// moves used to estimate game duration with increment or delay
+const CATEGORY_MOVES: u64 = 40;
+
+// rating category, from estimated game duration in seconds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessCategory {
+    // less than 3 minutes
+    Bullet,
+    // less than 8 minutes
+    Blitz,
+    // less than 25 minutes
+    Rapid,
+    // less than 1 day
+    Classical,
+    // 1 day or more, or no time limit
+    Correspondence,
+}
+
+impl CwChessCategory {
+    pub const ALL: [CwChessCategory; 5] = [
+        CwChessCategory::Bullet,
+        CwChessCategory::Blitz,
+        CwChessCategory::Rapid,
+        CwChessCategory::Classical,
+        CwChessCategory::Correspondence,
+    ];
+
+    // storage key
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CwChessCategory::Bullet => "bullet",
+            CwChessCategory::Blitz => "blitz",
+            CwChessCategory::Rapid => "rapid",
+            CwChessCategory::Classical => "classical",
+            CwChessCategory::Correspondence => "correspondence",
+        }
+    }
+}
+
+impl From<&CwChessTimeControl> for CwChessCategory {
+    fn from(time_control: &CwChessTimeControl) -> CwChessCategory {
+        let per_move = time_control
+            .increment_blocks
+            .unwrap_or(0)
+            .saturating_add(time_control.delay_blocks.unwrap_or(0));
+        let block_limit_duration = time_control
+            .block_limit
+            .map(|block_limit| block_limit.saturating_add(per_move.saturating_mul(CATEGORY_MOVES)));
+        // each move may use all of move_limit
+        let move_limit_duration = time_control
+            .move_limit
+            .map(|move_limit| move_limit.saturating_mul(CATEGORY_MOVES));
+        // game can't last longer than either limit allows
+        let duration = match block_limit_duration
+            .into_iter()
+            .chain(move_limit_duration)
+            .min()
+        {
+            Some(duration) => duration,
+            None => return CwChessCategory::Correspondence,
+        };
+        let seconds = match time_control.clock {
+            CwChessClock::Blocks => duration.saturating_mul(SECONDS_PER_BLOCK),
+            CwChessClock::Seconds => duration,
+        };
+        match seconds {
+            0..=179 => CwChessCategory::Bullet,
+            180..=479 => CwChessCategory::Blitz,
+            480..=1499 => CwChessCategory::Rapid,
+            1500..=86399 => CwChessCategory::Classical,
+            _ => CwChessCategory::Correspondence,
+        }
+    }
+}
+
+// draw may be claimed after threefold repetition or 50 moves (100 plies)
+const CLAIM_DRAW_REPETITIONS: usize = 3;
+const CLAIM_DRAW_HALFMOVES: u16 = 100;
+// game is drawn after fivefold repetition or 75 moves (150 plies)
+const AUTOMATIC_DRAW_REPETITIONS: usize = 5;
+const AUTOMATIC_DRAW_HALFMOVES: u16 = 150;
+
+// takebacks undo at most the requesting player's move and their opponent's reply
+const MAX_TAKEBACK_PLIES: usize = 2;
+
+// how player colors were assigned when challenge was accepted
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessColorAssignment {
+    // challenge creator chose color with play_as
+    PlayAs,
+    // sha256 of challenge id, block height, block time and both player addresses,
+    // challenge creator is white when first byte of hash is even
+    Hash {
+        challenge_id: u64,
+        block_time: Timestamp,
+        hash: Binary,
+    },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct CwChessGame {
-    // per player block limit for all moves
+    // per player time limit for all moves, in blocks or seconds depending on clock
     // starts at first move (not game start_height)
     pub block_limit: Option<u64>,
     // when game was created
     pub block_start: u64,
+    // None for games created before categories, see get_category
+    pub category: Option<CwChessCategory>,
+    // starting position number (0-959) for Chess960 games
+    pub chess960_position: Option<u16>,
+    // how block_limit, delay_blocks and increment_blocks are measured
+    #[serde(default)]
+    pub clock: CwChessClock,
+    // None for games created before color assignment was recorded
+    pub color_assignment: Option<CwChessColorAssignment>,
+    // time at start of each move that doesn't count toward block_limit
+    pub delay_blocks: Option<u64>,
+    // color of player with open draw offer
+    // offer stays open until accepted, declined or withdrawn, or opponent moves
+    pub draw_offer: Option<CwChessColor>,
     // board position in FEN
     // cheaper to load board than executing moves
     pub fen: String,
+    // block height, player color and action that ended game, when not a move
+    // (abort, accept draw or resign), since these aren't added to moves
+    pub final_action: Option<(u64, CwChessColor, CwChessAction)>,
+    // time allowed for each player's first move, measured using clock
+    // game is aborted if either player doesn't make their first move in time
+    pub first_move_limit: Option<u64>,
     // game id
     pub game_id: u64,
+    // time added to block_limit after each move
+    pub increment_blocks: Option<u64>,
+    // time allowed for each move after first move, measured using clock
+    // unlike block_limit, unused time is not saved for later moves
+    pub move_limit: Option<u64>,
+    // block time in seconds of each move
+    // stored separately so moves from existing games can still be loaded
+    #[serde(default)]
+    pub move_times: Vec<u64>,
     // list of moves
     pub moves: Vec<CwChessMove>,
     // player1 is white
     pub player1: Addr,
     // player2 is black
     pub player2: Addr,
+    // hash of each position reached, starting with initial position
+    #[serde(default)]
+    pub positions: Vec<u64>,
+    // ratings are updated when rated games end
+    #[serde(default)]
+    pub rated: bool,
+    // previous game when started by a rematch
+    pub rematch_of: Option<u64>,
+    // game started by a rematch of this game, only one rematch is allowed
+    pub rematched_by: Option<u64>,
+    // starting position in FEN, None for standard starting position
+    pub start_fen: Option<String>,
     // status is None while game is being played
     pub status: Option<CwChessGameOver>,
+    // block height when a takeback was last accepted
+    // inactivity for abandonment claims is measured from here or the last move
+    #[serde(default)]
+    pub takeback_height: Option<u64>,
+    // color of player asking to undo their last move
+    pub takeback_request: Option<CwChessColor>,
+    // time when a takeback was last accepted, measured using clock
+    // move limits for the next move start from here instead of the previous move
+    #[serde(default)]
+    pub takeback_time: Option<u64>,
+    // time used by (white, black) when a takeback was last accepted, measured using clock
+    // kept since undone moves are removed, time after takeback_time is added to it
+    #[serde(default)]
+    pub takeback_used_times: (u64, u64),
+    // block time in seconds when game was created
+    #[serde(default)]
+    pub time_start: u64,
+    // position in FEN before each of the last MAX_TAKEBACK_PLIES moves,
+    // only kept for casual games to allow takebacks
+    #[serde(default)]
+    pub undo_fens: Vec<String>,
+    #[serde(default)]
+    pub variant: CwChessVariant,
+    // amount each player staked, held by contract until game over
+    pub wager: Option<Wager>,
 }
 
 impl CwChessGame {
-    // check if game timed out based on block_time_limit
+    // check whether player to move may claim a draw
+    pub fn can_claim_draw(&self) -> bool {
+        self.status.is_none()
+            && (self.repetitions() >= CLAIM_DRAW_REPETITIONS
+                || fen::halfmove_clock(&self.fen) >= CLAIM_DRAW_HALFMOVES)
+    }
+
+    // waiting player may claim win when player to move has not moved for inactivity_blocks
+    // games with block_limit or move_limit use DeclareTimeout instead
+    // games without moves can only be aborted, since a first move no-show has no result
+    pub fn claim_abandonment(
+        &mut self,
+        player: &Addr,
+        block: &BlockInfo,
+        inactivity_blocks: u64,
+    ) -> Result<&Option<CwChessGameOver>, ContractError> {
+        let turn_color = match self.turn_color() {
+            Some(turn_color) => turn_color,
+            None => return Err(ContractError::GameAlreadyOver {}),
+        };
+        if self.get_player(&opposite(&turn_color)) != player {
+            return Err(ContractError::Unauthorized {});
+        }
+        if self.block_limit.is_some() || self.move_limit.is_some() {
+            return Err(ContractError::GameNotAbandoned {});
+        }
+        let last_move = match self.moves.last() {
+            Some(last_move) => last_move.0,
+            None => return Err(ContractError::GameNotAbandoned {}),
+        };
+        // player to move has had their move undone since then
+        let since = last_move.max(self.takeback_height.unwrap_or_default());
+        if block.height.saturating_sub(since) <= inactivity_blocks {
+            return Err(ContractError::GameNotAbandoned {});
+        }
+        self.status = match turn_color {
+            CwChessColor::White => Some(CwChessGameOver::WhiteAbandons),
+            CwChessColor::Black => Some(CwChessGameOver::BlackAbandons),
+        };
+        Ok(&self.status)
+    }
+
+    // check if game timed out based on first_move_limit, move_limit or block_limit
     pub fn check_timeout(
         &mut self,
-        current_block: u64,
+        block: &BlockInfo,
     ) -> Result<&Option<CwChessGameOver>, ContractError> {
         // check if game already over
         if self.status.is_some() {
             return Err(ContractError::GameAlreadyOver {});
         }
+        if let Some(turn_color) = self.turn_color() {
+            let move_used = self.get_move_used(block);
+            for (limit, status) in self.get_move_limits(&turn_color) {
+                if move_used > limit {
+                    self.status = Some(status);
+                    return Ok(&self.status);
+                }
+            }
+        }
         self.status = match self.block_limit {
             None => None,
-            Some(block_time_limit) => {
-                let block_times = self.get_block_times(current_block);
-                if block_times.0 > block_time_limit {
+            Some(block_limit) => {
+                let used_times = self.get_used_times(block);
+                let limits = self.get_limits(block_limit);
+                if used_times.0 > limits.0 {
                     Some(CwChessGameOver::WhiteTimeout {})
-                } else if block_times.1 > block_time_limit {
+                } else if used_times.1 > limits.1 {
                     Some(CwChessGameOver::BlackTimeout {})
                 } else {
                     None
@@ -136,17 +492,82 @@ impl CwChessGame {
         Ok(&self.status)
     }
 
+    // time remaining for (white, black), None if game has no time limit
+    // uses the same limits as check_timeout, including first_move_limit
+    pub fn get_remaining_times(&self, block: &BlockInfo) -> (Option<u64>, Option<u64>) {
+        let used_times = self.get_used_times(block);
+        let remaining = match self.block_limit {
+            None => (None, None),
+            Some(block_limit) => {
+                let limits = self.get_limits(block_limit);
+                (
+                    Some(limits.0.saturating_sub(used_times.0)),
+                    Some(limits.1.saturating_sub(used_times.1)),
+                )
+            }
+        };
+        // only player to move is using time toward move limits
+        let turn_color = self.turn_color();
+        let move_used = self.get_move_used(block);
+        let move_remaining = |color: CwChessColor, remaining: Option<u64>| -> Option<u64> {
+            let used = if turn_color == Some(color.clone()) {
+                move_used
+            } else {
+                0
+            };
+            self.get_move_limits(&color)
+                .into_iter()
+                .map(|(limit, _)| limit.saturating_sub(used))
+                .chain(remaining)
+                .min()
+        };
+        (
+            move_remaining(CwChessColor::White, remaining.0),
+            move_remaining(CwChessColor::Black, remaining.1),
+        )
+    }
+
+    // time used by (white, black), measured using clock
+    pub fn get_used_times(&self, block: &BlockInfo) -> (u64, u64) {
+        let mut used_times = self.takeback_used_times;
+        // time starts at first move
+        if self.moves.is_empty() {
+            return used_times;
+        }
+        let mut times = self.get_move_times();
+        // if game not over, add current time to end
+        if self.status.is_none() {
+            times.push(self.clock.now(block));
+        }
+        let delay = self.delay_blocks.unwrap_or_default();
+        let white_moves_first = self.white_moves_first();
+        // time before last takeback is already in takeback_used_times
+        let since = self.takeback_time.unwrap_or_default();
+        for i in 1..times.len() {
+            let move_time = times[i]
+                .saturating_sub(times[i - 1].max(since))
+                .saturating_sub(delay);
+            // even moves are made by first player
+            if (i % 2 == 0) == white_moves_first {
+                used_times.0 += move_time;
+            } else {
+                used_times.1 += move_time;
+            }
+        }
+        used_times
+    }
+
     pub fn get_player_order(
         player1: Addr,
         player2: Addr,
         play_as: Option<CwChessColor>,
-        height: u64,
+        hash: &Binary,
     ) -> (Addr, Addr) {
         match play_as {
             Some(CwChessColor::White) => (player1, player2),
             Some(CwChessColor::Black) => (player2, player1),
             None => {
-                if height % 2 == 0 {
+                if hash.as_slice()[0] % 2 == 0 {
                     (player1, player2)
                 } else {
                     (player2, player1)
@@ -155,99 +576,442 @@ impl CwChessGame {
         }
     }
 
+    // hash used to assign colors and choose Chess960 starting position
+    // anyone can verify the result, but it isn't secret when the challenge is accepted,
+    // so the accepting player (or block proposer) can refuse blocks with an unwanted result
+    pub fn get_hash(
+        challenge_id: u64,
+        block: &BlockInfo,
+        player1: &Addr,
+        player2: &Addr,
+    ) -> Binary {
+        // addresses are sorted so hash does not depend on who created challenge
+        let (first, second) = if player1 < player2 {
+            (player1, player2)
+        } else {
+            (player2, player1)
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(challenge_id.to_be_bytes());
+        hasher.update(block.height.to_be_bytes());
+        hasher.update(block.time.nanos().to_be_bytes());
+        hasher.update(first.as_bytes());
+        hasher.update(second.as_bytes());
+        Binary::from(hasher.finalize().to_vec())
+    }
+
+    // Chess960 starting position from hash bytes after the color assignment byte
+    // a u64 is used so every position is equally likely (within 1 in 10^16)
+    pub fn get_chess960_position(hash: &Binary) -> u16 {
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&hash.as_slice()[1..9]);
+        (u64::from_be_bytes(seed) % chess960::CHESS960_POSITIONS as u64) as u16
+    }
+
+    pub fn get_category(&self) -> CwChessCategory {
+        match &self.category {
+            Some(category) => category.clone(),
+            None => CwChessCategory::from(&self.get_time_control()),
+        }
+    }
+
+    pub fn get_time_control(&self) -> CwChessTimeControl {
+        CwChessTimeControl {
+            block_limit: self.block_limit,
+            clock: self.clock.clone(),
+            delay_blocks: self.delay_blocks,
+            first_move_limit: self.first_move_limit,
+            increment_blocks: self.increment_blocks,
+            move_limit: self.move_limit,
+        }
+    }
+
+    pub fn get_player_color(&self, player: &Addr) -> Option<CwChessColor> {
+        if *player == self.player1 {
+            Some(CwChessColor::White)
+        } else if *player == self.player2 {
+            Some(CwChessColor::Black)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_player(&self, color: &CwChessColor) -> &Addr {
+        match color {
+            CwChessColor::White => &self.player1,
+            CwChessColor::Black => &self.player2,
+        }
+    }
+
     pub fn load_game(&self) -> Result<Game, ContractError> {
-        match Game::from_fen(
-            &self.fen,
-            self.draw_offered().as_ref().map(Color::from),
-            None,
-        ) {
+        // chess-engine doesn't support Chess960 castling rights
+        let fen = match self.variant {
+            CwChessVariant::Chess960 => fen::with_castling(&self.fen, "-"),
+            CwChessVariant::Standard => self.fen.clone(),
+        };
+        match Game::from_fen(&fen, self.draw_offer.as_ref().map(Color::from), None) {
             Ok(game) => Ok(game),
             Err(_) => Err(ContractError::InvalidPosition {}),
         }
     }
 
+    // casual games aren't rated and don't have a wager, and allow takebacks
+    pub fn is_casual(&self) -> bool {
+        !self.rated && self.wager.is_none()
+    }
+
+    // check whether DeclareTimeout would end game now
+    pub fn is_flagged(&self, block: &BlockInfo) -> bool {
+        matches!(self.clone().check_timeout(block), Ok(Some(_)))
+    }
+
     pub fn make_move(
         &mut self,
         player: &Addr,
-        chess_move: CwChessMove,
+        block: &BlockInfo,
+        action: CwChessAction,
     ) -> Result<&Option<CwChessGameOver>, ContractError> {
         // check if game already over
         if self.status.is_some() {
             return Err(ContractError::GameAlreadyOver {});
         }
         // check if game timed out
-        if self.check_timeout(chess_move.0)?.is_some() {
+        if self.check_timeout(block)?.is_some() {
             // check_timeout updates and returns status
             return Ok(&self.status);
         }
+        // actions other than moves may be taken on either player's turn
+        match action {
+            CwChessAction::Abort
+            | CwChessAction::AcceptDraw
+            | CwChessAction::AcceptTakeback
+            | CwChessAction::DeclineDraw
+            | CwChessAction::DeclineTakeback
+            | CwChessAction::OfferDrawWithoutMove
+            | CwChessAction::RequestTakeback
+            | CwChessAction::Resign
+            | CwChessAction::WithdrawDraw => {
+                let color = match self.get_player_color(player) {
+                    Some(color) => color,
+                    None => return Err(ContractError::Unauthorized {}),
+                };
+                self.take_action(&color, block, &action)?;
+                if self.status.is_some() {
+                    self.final_action = Some((block.height, color, action));
+                }
+                return Ok(&self.status);
+            }
+            _ => {}
+        }
         let mut game = self.load_game()?;
-        let player_to_move = match game.get_turn_color() {
-            Color::White => &self.player1,
-            Color::Black => &self.player2,
+        // fullmove number increments after black moves
+        let player_color = CwChessColor::from(&game.get_turn_color());
+        let player_to_move = self.get_player(&player_color);
+        let fullmove_increment = match player_color {
+            CwChessColor::White => 0,
+            CwChessColor::Black => 1,
         };
         if player_to_move != player {
             return Err(ContractError::NotYourTurn {});
         }
-        match game.make_move(&GameAction::from(&chess_move.1)) {
-            Err(_) => Err(ContractError::InvalidMove {}),
-            Ok(status) => {
-                self.moves.push(chess_move);
-                self.status = status.as_ref().map(CwChessGameOver::from);
-                self.fen = game.to_fen(0, (self.moves.len() / 2) as u8).unwrap();
-                Ok(&self.status)
+        if action == CwChessAction::ClaimDraw {
+            if !self.can_claim_draw() {
+                return Err(ContractError::CannotClaimDraw {});
+            }
+            self.push_move(block, action);
+            self.status = Some(CwChessGameOver::DrawDeclared);
+            return Ok(&self.status);
+        }
+        let (status, fen) = match self.variant {
+            CwChessVariant::Chess960 => chess960::make_move(&self.fen, &mut game, &action)?,
+            CwChessVariant::Standard => match game.make_move(&GameAction::try_from(&action)?) {
+                Err(_) => return Err(ContractError::InvalidMove {}),
+                Ok(status) => (
+                    status.as_ref().map(CwChessGameOver::from),
+                    game.to_fen(0, 0).unwrap(),
+                ),
+            },
+        };
+        if self.is_casual() {
+            self.undo_fens.push(self.fen.clone());
+            if self.undo_fens.len() > MAX_TAKEBACK_PLIES {
+                self.undo_fens.remove(0);
             }
         }
+        // moving declines opponent's takeback request and draw offer
+        self.takeback_request = None;
+        self.draw_offer = match action {
+            CwChessAction::OfferDraw(_) => Some(player_color),
+            _ => self
+                .draw_offer
+                .clone()
+                .filter(|color| *color == player_color),
+        };
+        self.push_move(block, action);
+        self.status = status;
+        let fullmove = fen::fullmove_number(&self.fen).saturating_add(fullmove_increment);
+        let halfmove_clock = if fen::is_irreversible(&self.fen, &fen) {
+            0
+        } else {
+            fen::halfmove_clock(&self.fen).saturating_add(1)
+        };
+        self.fen = fen::with_clocks(&fen, halfmove_clock, fullmove);
+        if self.status.is_none() {
+            self.positions.push(fen::position_key(&self.fen));
+            self.check_automatic_draw();
+        }
+        Ok(&self.status)
     }
 
     pub fn turn_color(&self) -> Option<CwChessColor> {
         match self.status {
-            None => match self.moves.len() % 2 {
-                0 => Some(CwChessColor::White),
-                1 => Some(CwChessColor::Black),
-                // rust can't tell this is impossible
-                _ => None,
-            },
+            None => fen::turn_color(&self.fen),
             _ => None,
         }
     }
 
-    // check whether draw was offered on previous turn
-    // return color that offered draw
-    fn draw_offered(&self) -> Option<CwChessColor> {
-        match &self.moves.last() {
-            Some((_, CwChessAction::OfferDraw(_))) => {
-                match self.turn_color() {
-                    None => None,
-                    // current turn means opposite color offered draw
-                    Some(CwChessColor::Black) => Some(CwChessColor::White),
-                    Some(CwChessColor::White) => Some(CwChessColor::Black),
-                }
+    // check that a starting position can be loaded and played
+    pub fn validate_fen(fen: &str) -> Result<(), ContractError> {
+        if Game::from_fen(fen, None, None).is_err()
+            || fen::clocks(fen).is_none()
+            || fen::is_insufficient_material(fen)
+        {
+            return Err(ContractError::InvalidPosition {});
+        }
+        let board = Board::from_fen(fen).ok_or(ContractError::InvalidPosition {})?;
+        // player not to move can't be in check, and game can't already be over
+        let waiting = Board {
+            turn_color: opposite(&board.turn_color),
+            ..board.clone()
+        };
+        if waiting.is_check() || !board.has_legal_move() {
+            return Err(ContractError::InvalidPosition {});
+        }
+        Ok(())
+    }
+
+    // end game when neither player can checkmate,
+    // or after fivefold repetition or 75 move rule
+    fn check_automatic_draw(&mut self) {
+        if fen::is_insufficient_material(&self.fen) {
+            self.status = Some(CwChessGameOver::InsufficientMaterial);
+        } else if self.repetitions() >= AUTOMATIC_DRAW_REPETITIONS
+            || fen::halfmove_clock(&self.fen) >= AUTOMATIC_DRAW_HALFMOVES
+        {
+            self.status = Some(CwChessGameOver::DrawDeclared);
+        }
+    }
+
+    // limits for (white, black), including increments for moves made
+    fn get_limits(&self, block_limit: u64) -> (u64, u64) {
+        let increment = self.increment_blocks.unwrap_or_default();
+        let moves_made = self.get_moves_made();
+        (
+            block_limit.saturating_add(moves_made.0.saturating_mul(increment)),
+            block_limit.saturating_add(moves_made.1.saturating_mul(increment)),
+        )
+    }
+
+    // limits for color's current or next move, with result when limit is exceeded,
+    // in the order check_timeout checks them
+    fn get_move_limits(&self, color: &CwChessColor) -> Vec<(u64, CwChessGameOver)> {
+        let mut limits = vec![];
+        // clock doesn't start until first move, so first moves have separate limit
+        let moves_made = self.get_moves_made();
+        let player_moves = match color {
+            CwChessColor::White => moves_made.0,
+            CwChessColor::Black => moves_made.1,
+        };
+        if let (Some(first_move_limit), 0) = (self.first_move_limit, player_moves) {
+            limits.push((first_move_limit, CwChessGameOver::Aborted));
+        }
+        // unused time from earlier moves doesn't carry over to move_limit,
+        // which starts after the first move of the game
+        let is_turn = self.turn_color().as_ref() == Some(color);
+        if let Some(move_limit) = self.move_limit {
+            if !self.moves.is_empty() || !is_turn {
+                let timeout = match color {
+                    CwChessColor::White => CwChessGameOver::WhiteTimeout,
+                    CwChessColor::Black => CwChessGameOver::BlackTimeout,
+                };
+                limits.push((move_limit, timeout));
             }
-            _ => None,
         }
+        limits
     }
 
-    // get number of blocks used by each player
-    fn get_block_times(&self, current_block: u64) -> (u64, u64) {
-        // block times for (white, black)
-        let mut block_times: (u64, u64) = (0, 0);
-        // block time starts at first move
-        if self.moves.is_empty() {
-            return block_times;
+    // time used by player to move since previous move, or since game start,
+    // restarting when a takeback is accepted
+    fn get_move_used(&self, block: &BlockInfo) -> u64 {
+        let since = match self.get_move_times().last() {
+            Some(last_move) => *last_move,
+            None => self.get_start_time(),
+        };
+        let since = since.max(self.takeback_time.unwrap_or_default());
+        self.clock.now(block).saturating_sub(since)
+    }
+
+    // number of moves made by (white, black)
+    fn get_moves_made(&self) -> (u64, u64) {
+        // first player has made the extra move when number of moves is odd
+        let second_moves = self.moves.len() as u64 / 2;
+        let first_moves = self.moves.len() as u64 - second_moves;
+        if self.white_moves_first() {
+            (first_moves, second_moves)
+        } else {
+            (second_moves, first_moves)
         }
-        let mut blocks: Vec<u64> = self.moves.iter().map(|m| -> u64 { m.0 }).collect();
-        // if game not over, add current block to end
-        if self.status.is_none() {
-            blocks.push(current_block);
+    }
+
+    // time of each move, measured using clock
+    fn get_move_times(&self) -> Vec<u64> {
+        match self.clock {
+            CwChessClock::Blocks => self.moves.iter().map(|m| -> u64 { m.0 }).collect(),
+            CwChessClock::Seconds => self.move_times.clone(),
         }
-        for i in 1..blocks.len() {
-            let move_time = blocks[i] - blocks[i - 1];
-            if i % 2 == 0 {
-                block_times.0 += move_time;
-            } else {
-                block_times.1 += move_time;
+    }
+
+    // time when game was created, measured using clock
+    fn get_start_time(&self) -> u64 {
+        match self.clock {
+            CwChessClock::Blocks => self.block_start,
+            CwChessClock::Seconds => self.time_start,
+        }
+    }
+
+    fn push_move(&mut self, block: &BlockInfo, action: CwChessAction) {
+        self.moves.push((block.height, action));
+        self.move_times.push(block.time.seconds());
+    }
+
+    // number of times current position has occurred
+    fn repetitions(&self) -> usize {
+        match self.positions.last() {
+            None => 0,
+            Some(current) => self.positions.iter().filter(|p| *p == current).count(),
+        }
+    }
+
+    // take action that isn't a move
+    fn take_action(
+        &mut self,
+        color: &CwChessColor,
+        block: &BlockInfo,
+        action: &CwChessAction,
+    ) -> Result<&Option<CwChessGameOver>, ContractError> {
+        let opponent = Some(opposite(color));
+        match action {
+            // players may abort game until they have made their first move
+            CwChessAction::Abort => {
+                let moves_made = self.get_moves_made();
+                let player_moves = match color {
+                    CwChessColor::White => moves_made.0,
+                    CwChessColor::Black => moves_made.1,
+                };
+                if player_moves > 0 {
+                    return Err(ContractError::CannotAbort {});
+                }
+                self.status = Some(CwChessGameOver::Aborted);
+            }
+            CwChessAction::AcceptDraw | CwChessAction::DeclineDraw => {
+                if self.draw_offer != opponent {
+                    return Err(ContractError::InvalidMove {});
+                }
+                if *action == CwChessAction::AcceptDraw {
+                    self.status = Some(CwChessGameOver::DrawAccepted);
+                }
+                self.draw_offer = None;
+            }
+            CwChessAction::OfferDrawWithoutMove => {
+                if self.draw_offer.is_some() {
+                    return Err(ContractError::InvalidMove {});
+                }
+                self.draw_offer = Some(color.clone());
             }
+            CwChessAction::AcceptTakeback | CwChessAction::DeclineTakeback => {
+                if self.takeback_request != opponent {
+                    return Err(ContractError::InvalidMove {});
+                }
+                if *action == CwChessAction::AcceptTakeback {
+                    // players keep time used on undone moves,
+                    // requesting player's move starts again now
+                    let used_times = self.get_used_times(block);
+                    self.undo_moves(&opposite(color))?;
+                    self.takeback_height = Some(block.height);
+                    self.takeback_time = Some(self.clock.now(block));
+                    self.takeback_used_times = used_times;
+                }
+                self.takeback_request = None;
+            }
+            CwChessAction::RequestTakeback => {
+                let moves_made = self.get_moves_made();
+                let player_moves = match color {
+                    CwChessColor::White => moves_made.0,
+                    CwChessColor::Black => moves_made.1,
+                };
+                if !self.is_casual() || player_moves == 0 || !self.can_undo(color) {
+                    return Err(ContractError::TakebackNotAllowed {});
+                }
+                if self.takeback_request.is_some() {
+                    return Err(ContractError::InvalidMove {});
+                }
+                self.takeback_request = Some(color.clone());
+            }
+            CwChessAction::Resign => {
+                self.status = match color {
+                    CwChessColor::White => Some(CwChessGameOver::WhiteResigns),
+                    CwChessColor::Black => Some(CwChessGameOver::BlackResigns),
+                };
+                self.draw_offer = None;
+            }
+            CwChessAction::WithdrawDraw => {
+                if self.draw_offer.as_ref() != Some(color) {
+                    return Err(ContractError::InvalidMove {});
+                }
+                self.draw_offer = None;
+            }
+            _ => return Err(ContractError::InvalidMove {}),
+        }
+        Ok(&self.status)
+    }
+
+    // check that positions needed to undo color's last move are kept
+    fn can_undo(&self, color: &CwChessColor) -> bool {
+        let plies = self.undo_plies(color);
+        self.undo_fens.len() >= plies && self.moves.len() >= plies
+    }
+
+    // undo moves until it is color's turn again
+    fn undo_moves(&mut self, color: &CwChessColor) -> Result<(), ContractError> {
+        if !self.can_undo(color) {
+            return Err(ContractError::TakebackNotAllowed {});
+        }
+        for _ in 0..self.undo_plies(color) {
+            self.moves.pop();
+            self.move_times.pop();
+            self.positions.pop();
+            if let Some(fen) = self.undo_fens.pop() {
+                self.fen = fen;
+            }
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    // number of moves to undo color's last move, including opponent's reply
+    fn undo_plies(&self, color: &CwChessColor) -> usize {
+        if self.turn_color().as_ref() == Some(color) {
+            MAX_TAKEBACK_PLIES
+        } else {
+            1
+        }
+    }
+
+    // starting position may have black to move
+    fn white_moves_first(&self) -> bool {
+        match &self.start_fen {
+            Some(start_fen) => fen::turn_color(start_fen) != Some(CwChessColor::Black),
+            None => true,
         }
-        block_times
     }
 }
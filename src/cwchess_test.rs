@@ -0,0 +1,742 @@
+#[cfg(test)]
+mod tests {
+    use crate::cwchess::{
+        CwChessAction, CwChessCategory, CwChessClock, CwChessColor, CwChessGame, CwChessGameOver,
+        CwChessTimeControl, CwChessVariant,
+    };
+    use crate::error::ContractError;
+    use crate::fen::{castling, fullmove_number, halfmove_clock, position_key};
+    use crate::wager::Wager;
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{coin, Addr, Binary, BlockInfo, Timestamp};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    // create block info for a block height, with blocks every 5 seconds
+    fn block(height: u64) -> BlockInfo {
+        let mut block = mock_env().block;
+        block.height = height;
+        block.time = Timestamp::from_seconds(height * 5);
+        block
+    }
+
+    // create a game from a position
+    fn fen_game(fen: &str) -> CwChessGame {
+        CwChessGame {
+            block_limit: None,
+            block_start: 0,
+            category: None,
+            chess960_position: None,
+            clock: CwChessClock::Blocks,
+            color_assignment: None,
+            delay_blocks: None,
+            draw_offer: None,
+            fen: fen.to_string(),
+            final_action: None,
+            first_move_limit: None,
+            game_id: 1,
+            increment_blocks: None,
+            move_limit: None,
+            move_times: vec![],
+            moves: vec![],
+            player1: Addr::unchecked("white"),
+            player2: Addr::unchecked("black"),
+            positions: vec![position_key(fen)],
+            rated: false,
+            rematch_of: None,
+            rematched_by: None,
+            start_fen: Some(fen.to_string()),
+            status: None,
+            takeback_height: None,
+            takeback_request: None,
+            takeback_time: None,
+            takeback_used_times: (0, 0),
+            time_start: 0,
+            undo_fens: vec![],
+            variant: CwChessVariant::Standard,
+            wager: None,
+        }
+    }
+
+    #[test]
+    fn test_halfmove_clock() {
+        let mut game = fen_game("r3k3/8/8/8/8/8/P7/4K2R w - - 10 40");
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        // rook move increments clock
+        game.make_move(&white, &block(1), "Rh7".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 11);
+        // king move increments clock
+        game.make_move(&black, &block(2), "Kd8".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 12);
+        // pawn move resets clock
+        game.make_move(&white, &block(3), "a4".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 0);
+        game.make_move(&black, &block(4), "Kc8".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 1);
+        game.make_move(&white, &block(5), "Kd2".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 2);
+        // capture resets clock
+        game.make_move(&black, &block(6), "Rxa4".into()).unwrap();
+        assert_eq!(halfmove_clock(&game.fen), 0);
+    }
+
+    #[test]
+    fn test_fullmove_number() {
+        let mut game = fen_game("r3k3/8/8/8/8/8/P7/4K2R w - - 10 255");
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(1), "Rh7".into()).unwrap();
+        game.make_move(&black, &block(2), "Kd8".into()).unwrap();
+        assert_eq!(fullmove_number(&game.fen), 256);
+        // starting position clocks must be valid numbers
+        assert!(CwChessGame::validate_fen("r3k3/8/8/8/8/8/P7/4K2R w - - 10 300").is_ok());
+        match CwChessGame::validate_fen("r3k3/8/8/8/8/8/P7/4K2R w - - 10 999999999999") {
+            Err(ContractError::InvalidPosition {}) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_validate_fen() {
+        assert!(CwChessGame::validate_fen(START).is_ok());
+        let invalid = |fen: &str| match CwChessGame::validate_fen(fen).unwrap_err() {
+            ContractError::InvalidPosition { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        };
+        // black in check with white to move
+        invalid("4k3/8/8/8/8/8/8/4RK2 w - - 0 1");
+        // black is checkmated
+        invalid("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        // black is stalemated
+        invalid("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    }
+
+    #[test]
+    fn test_fifty_move_rule() {
+        let mut game = fen_game("4k3/8/8/8/8/8/8/4K2R w - - 99 90");
+        let white = Addr::unchecked("white");
+
+        assert!(!game.can_claim_draw());
+        game.make_move(&white, &block(1), "Rh7".into()).unwrap();
+        // 50 moves can be claimed
+        assert!(game.can_claim_draw());
+        assert_eq!(game.status, None);
+    }
+
+    #[test]
+    fn test_seventy_five_move_rule() {
+        let mut game = fen_game("4k3/8/8/8/8/8/8/4K2R w - - 149 90");
+        let white = Addr::unchecked("white");
+
+        // 75 moves ends game
+        game.make_move(&white, &block(1), "Rh7".into()).unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::DrawDeclared));
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let mut game = fen_game("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1");
+        game.variant = CwChessVariant::Chess960;
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(1), "O-O".into()).unwrap();
+        assert_eq!(
+            game.fen,
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b gb - 1 1"
+        );
+        // rook move removes castling right
+        game.make_move(&black, &block(2), "Rf8".into()).unwrap();
+        assert_eq!(castling(&game.fen), "b");
+        game.make_move(&white, &block(3), "a3".into()).unwrap();
+        game.make_move(&black, &block(4), "O-O-O".into()).unwrap();
+        assert_eq!(
+            game.fen,
+            "2kr1r2/pppppppp/8/8/8/P7/1PPPPPPP/1R3RK1 w - - 1 3"
+        );
+        assert_eq!(game.moves.len(), 4);
+        // no castling rights left
+        match game
+            .make_move(&white, &block(5), "O-O-O".into())
+            .unwrap_err()
+        {
+            ContractError::InvalidMove { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_color_assignment() {
+        let creator = Addr::unchecked("creator");
+        let other = Addr::unchecked("other");
+        let mut block = mock_env().block;
+
+        // hash does not depend on player order
+        let hash = CwChessGame::get_hash(1, &block, &creator, &other);
+        assert_eq!(hash, CwChessGame::get_hash(1, &block, &other, &creator));
+        assert_ne!(hash, CwChessGame::get_hash(2, &block, &creator, &other));
+        // block time is included
+        block.time = block.time.plus_nanos(1);
+        assert_ne!(hash, CwChessGame::get_hash(1, &block, &creator, &other));
+
+        // play_as is used when set
+        assert_eq!(
+            CwChessGame::get_player_order(
+                creator.clone(),
+                other.clone(),
+                Some(CwChessColor::Black),
+                &hash
+            ),
+            (other.clone(), creator.clone())
+        );
+        // otherwise first byte of hash
+        let even = Binary::from(vec![2, 1]);
+        let odd = Binary::from(vec![1, 2]);
+        assert_eq!(
+            CwChessGame::get_player_order(creator.clone(), other.clone(), None, &even),
+            (creator.clone(), other.clone())
+        );
+        assert_eq!(
+            CwChessGame::get_player_order(creator.clone(), other.clone(), None, &odd),
+            (other, creator)
+        );
+    }
+
+    #[test]
+    fn test_chess960_position() {
+        // bytes after the first are a big-endian u64
+        let mut hash = vec![0u8; 32];
+        hash[8] = 1;
+        assert_eq!(
+            CwChessGame::get_chess960_position(&Binary::from(hash.clone())),
+            1
+        );
+        hash[7] = 4;
+        // 1025 % 960
+        assert_eq!(
+            CwChessGame::get_chess960_position(&Binary::from(hash.clone())),
+            65
+        );
+        // first byte is used for colors
+        hash[0] = 255;
+        assert_eq!(CwChessGame::get_chess960_position(&Binary::from(hash)), 65);
+        let hash = Binary::from(vec![255u8; 32]);
+        // (2^64 - 1) % 960
+        assert_eq!(CwChessGame::get_chess960_position(&hash), 255);
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(100);
+        game.increment_blocks = Some(10);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        game.make_move(&black, &block(50), "e5".into()).unwrap();
+        // without increment white would time out after 100 blocks
+        let mut no_increment = game.clone();
+        no_increment.increment_blocks = None;
+        assert_eq!(
+            no_increment.check_timeout(&block(160)).unwrap(),
+            &Some(CwChessGameOver::WhiteTimeout)
+        );
+        // white has 10 extra blocks after first move
+        game.make_move(&white, &block(160), "Nf3".into()).unwrap();
+        assert_eq!(game.status, None);
+        // black used 50 and has 10 extra blocks after first move
+        assert_eq!(game.clone().check_timeout(&block(220)).unwrap(), &None);
+        assert_eq!(
+            game.check_timeout(&block(221)).unwrap(),
+            &Some(CwChessGameOver::BlackTimeout)
+        );
+    }
+
+    #[test]
+    fn test_increment_overflow() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(u64::MAX);
+        game.increment_blocks = Some(u64::MAX);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(1), "e4".into()).unwrap();
+        game.make_move(&black, &block(2), "e5".into()).unwrap();
+        game.make_move(&white, &block(3), "Nf3".into()).unwrap();
+        assert_eq!(game.check_timeout(&block(4)).unwrap(), &None);
+        assert_eq!(game.get_remaining_times(&block(4)).0, Some(u64::MAX - 1));
+        // game can still end
+        game.make_move(&black, &block(4), CwChessAction::Resign)
+            .unwrap();
+        assert_eq!(game.status, Some(CwChessGameOver::BlackResigns));
+        // time controls this large are rejected when creating challenges
+        let time_control = CwChessTimeControl {
+            increment_blocks: Some(u64::MAX),
+            ..CwChessTimeControl::default()
+        };
+        match time_control.validate() {
+            Err(ContractError::InvalidTimeControl {}) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        let time_control = CwChessTimeControl {
+            block_limit: Some(86_400),
+            increment_blocks: Some(60),
+            ..CwChessTimeControl::default()
+        };
+        assert!(time_control.validate().is_ok());
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(100);
+        game.delay_blocks = Some(20);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        // black uses 30 blocks
+        game.make_move(&black, &block(50), "e5".into()).unwrap();
+        // white uses 70 blocks
+        game.make_move(&white, &block(140), "Nf3".into()).unwrap();
+        // black uses 70 more blocks, delay is not saved for later moves
+        assert_eq!(game.clone().check_timeout(&block(230)).unwrap(), &None);
+        assert_eq!(
+            game.check_timeout(&block(231)).unwrap(),
+            &Some(CwChessGameOver::BlackTimeout)
+        );
+    }
+
+    #[test]
+    fn test_seconds_clock() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(300);
+        game.clock = CwChessClock::Seconds;
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+        // block time doesn't depend on block height
+        let block_at = |height: u64, seconds: u64| -> BlockInfo {
+            let mut block = block(height);
+            block.time = Timestamp::from_seconds(seconds);
+            block
+        };
+
+        game.make_move(&white, &block_at(1, 1000), "e4".into())
+            .unwrap();
+        game.make_move(&black, &block_at(2, 1100), "e5".into())
+            .unwrap();
+        assert_eq!(game.move_times, vec![1000, 1100]);
+        // white has used 300 seconds
+        assert_eq!(
+            game.clone().check_timeout(&block_at(3, 1400)).unwrap(),
+            &None
+        );
+        assert_eq!(
+            game.make_move(&white, &block_at(3, 1401), "Nf3".into())
+                .unwrap(),
+            &Some(CwChessGameOver::WhiteTimeout)
+        );
+    }
+
+    #[test]
+    fn test_move_limit() {
+        let mut game = fen_game(START);
+        game.move_limit = Some(100);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        game.make_move(&black, &block(90), "e5".into()).unwrap();
+        // fast move doesn't give white more time for later moves
+        game.make_move(&white, &block(100), "Nf3".into()).unwrap();
+        game.make_move(&black, &block(200), "Nc6".into()).unwrap();
+        assert_eq!(game.clone().check_timeout(&block(300)).unwrap(), &None);
+        // black has full move limit for next move
+        assert_eq!(game.get_remaining_times(&block(300)), (Some(0), Some(100)));
+        assert!(game.is_flagged(&block(301)));
+        assert_eq!(
+            game.make_move(&white, &block(301), "Bb5".into()).unwrap(),
+            &Some(CwChessGameOver::WhiteTimeout)
+        );
+    }
+
+    #[test]
+    fn test_first_move_limit() {
+        let mut game = fen_game(START);
+        game.first_move_limit = Some(50);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        // white must move within 50 blocks of game start
+        assert_eq!(game.clone().check_timeout(&block(50)).unwrap(), &None);
+        assert_eq!(
+            game.clone().check_timeout(&block(51)).unwrap(),
+            &Some(CwChessGameOver::Aborted)
+        );
+        // clock agrees with check_timeout
+        assert_eq!(game.get_remaining_times(&block(30)), (Some(20), Some(50)));
+        assert!(!game.is_flagged(&block(50)));
+        assert_eq!(game.get_remaining_times(&block(51)), (Some(0), Some(50)));
+        assert!(game.is_flagged(&block(51)));
+        // black must move within 50 blocks of white's first move
+        game.make_move(&white, &block(40), "e4".into()).unwrap();
+        assert_eq!(game.clone().check_timeout(&block(90)).unwrap(), &None);
+        assert_eq!(
+            game.clone().check_timeout(&block(91)).unwrap(),
+            &Some(CwChessGameOver::Aborted)
+        );
+        assert_eq!(game.get_remaining_times(&block(60)), (None, Some(30)));
+        // no limit after first moves
+        game.make_move(&black, &block(90), "e5".into()).unwrap();
+        assert_eq!(game.get_remaining_times(&block(1000)), (None, None));
+        assert_eq!(game.check_timeout(&block(1000)).unwrap(), &None);
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut game = fen_game(START);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        // only players can abort
+        match game
+            .clone()
+            .make_move(&Addr::unchecked("other"), &block(1), CwChessAction::Abort)
+            .unwrap_err()
+        {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        // black can abort before white moves
+        assert_eq!(
+            game.clone()
+                .make_move(&black, &block(1), CwChessAction::Abort)
+                .unwrap(),
+            &Some(CwChessGameOver::Aborted)
+        );
+        // white cannot abort after moving
+        game.make_move(&white, &block(1), "e4".into()).unwrap();
+        match game
+            .clone()
+            .make_move(&white, &block(2), CwChessAction::Abort)
+            .unwrap_err()
+        {
+            ContractError::CannotAbort { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            game.make_move(&black, &block(2), CwChessAction::Abort)
+                .unwrap(),
+            &Some(CwChessGameOver::Aborted)
+        );
+        assert_eq!(game.status.unwrap().winner(), None);
+    }
+
+    #[test]
+    fn test_draw_offer() {
+        let mut game = fen_game(START);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        // black offers draw on white's turn, white declines
+        game.make_move(&black, &block(1), CwChessAction::OfferDrawWithoutMove)
+            .unwrap();
+        assert_eq!(game.draw_offer, Some(CwChessColor::Black));
+        match game
+            .clone()
+            .make_move(&black, &block(1), CwChessAction::AcceptDraw)
+            .unwrap_err()
+        {
+            ContractError::InvalidMove { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        game.make_move(&white, &block(1), CwChessAction::DeclineDraw)
+            .unwrap();
+        assert_eq!(game.draw_offer, None);
+        // white offers and withdraws
+        game.make_move(&white, &block(1), CwChessAction::OfferDrawWithoutMove)
+            .unwrap();
+        game.make_move(&white, &block(1), CwChessAction::WithdrawDraw)
+            .unwrap();
+        assert_eq!(game.draw_offer, None);
+        // moving declines opponent's offer
+        game.make_move(
+            &white,
+            &block(1),
+            CwChessAction::OfferDraw("e4".to_string()),
+        )
+        .unwrap();
+        assert_eq!(game.draw_offer, Some(CwChessColor::White));
+        game.make_move(&black, &block(2), "e5".into()).unwrap();
+        assert_eq!(game.draw_offer, None);
+        // offer stays open after offering player moves
+        game.make_move(&white, &block(3), CwChessAction::OfferDrawWithoutMove)
+            .unwrap();
+        game.make_move(&white, &block(3), "Nf3".into()).unwrap();
+        assert_eq!(
+            game.make_move(&black, &block(4), CwChessAction::AcceptDraw)
+                .unwrap(),
+            &Some(CwChessGameOver::DrawAccepted)
+        );
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(
+            game.final_action,
+            Some((4, CwChessColor::Black, CwChessAction::AcceptDraw))
+        );
+    }
+
+    #[test]
+    fn test_resign_any_turn() {
+        let mut game = fen_game(START);
+        let black = Addr::unchecked("black");
+
+        assert_eq!(
+            game.make_move(&black, &block(1), CwChessAction::Resign)
+                .unwrap(),
+            &Some(CwChessGameOver::BlackResigns)
+        );
+        assert!(game.moves.is_empty());
+        // action that ended game is recorded
+        assert_eq!(
+            game.final_action,
+            Some((1, CwChessColor::Black, CwChessAction::Resign))
+        );
+    }
+
+    #[test]
+    fn test_takeback() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(45);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        // cannot request before moving
+        match game
+            .clone()
+            .make_move(&white, &block(0), CwChessAction::RequestTakeback)
+            .unwrap_err()
+        {
+            ContractError::TakebackNotAllowed { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        game.make_move(&black, &block(10), "e5".into()).unwrap();
+        game.make_move(&white, &block(30), "Nf3".into()).unwrap();
+        // only positions needed for a takeback are kept
+        assert_eq!(game.undo_fens.len(), 2);
+        let after_e5 = game.undo_fens[1].clone();
+        // black declines
+        game.make_move(&white, &block(35), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&black, &block(35), CwChessAction::DeclineTakeback)
+            .unwrap();
+        assert_eq!(game.takeback_request, None);
+        // black accepts, white's last move is undone
+        game.make_move(&white, &block(40), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&black, &block(40), CwChessAction::AcceptTakeback)
+            .unwrap();
+        assert_eq!(game.fen, after_e5);
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.positions.len(), 3);
+        // players keep time used on undone moves, white's move starts at takeback
+        assert_eq!(game.get_used_times(&block(40)), (20, 20));
+        assert_eq!(game.get_used_times(&block(50)), (30, 20));
+        assert!(!game.is_flagged(&block(65)));
+        assert!(game.is_flagged(&block(66)));
+        // position before black's reply was not kept
+        match game
+            .clone()
+            .make_move(&white, &block(50), CwChessAction::RequestTakeback)
+            .unwrap_err()
+        {
+            ContractError::TakebackNotAllowed { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // on requesting player's turn, opponent's reply is also undone
+        game.make_move(&white, &block(50), "Nc3".into()).unwrap();
+        game.make_move(&black, &block(55), "Nc6".into()).unwrap();
+        game.make_move(&white, &block(60), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&black, &block(60), CwChessAction::AcceptTakeback)
+            .unwrap();
+        assert_eq!(game.fen, after_e5);
+        assert_eq!(game.moves.len(), 2);
+        assert!(game.undo_fens.is_empty());
+        assert_eq!(game.get_used_times(&block(60)), (35, 25));
+        assert!(!game.is_flagged(&block(70)));
+        assert!(game.is_flagged(&block(71)));
+        game.make_move(&white, &block(70), "d4".into()).unwrap();
+        assert_eq!(game.get_used_times(&block(90)), (45, 45));
+    }
+
+    #[test]
+    fn test_takeback_block_limit() {
+        let mut game = fen_game(START);
+        game.block_limit = Some(500);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(100), "e4".into()).unwrap();
+        game.make_move(&black, &block(150), "e5".into()).unwrap();
+        game.make_move(&white, &block(600), "Nf3".into()).unwrap();
+        assert_eq!(game.get_used_times(&block(600)), (450, 50));
+        game.make_move(&black, &block(600), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&white, &block(601), CwChessAction::AcceptTakeback)
+            .unwrap();
+        // white's thinking time isn't moved to black
+        assert_eq!(game.get_used_times(&block(601)), (450, 51));
+        assert!(!game.is_flagged(&block(601)));
+        game.make_move(&black, &block(700), "c5".into()).unwrap();
+        assert_eq!(game.get_used_times(&block(750)), (500, 150));
+        assert!(game.is_flagged(&block(751)));
+    }
+
+    #[test]
+    fn test_takeback_abandonment() {
+        let mut game = fen_game(START);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        game.make_move(&black, &block(10), "e5".into()).unwrap();
+        game.make_move(&white, &block(20), "Nf3".into()).unwrap();
+        game.make_move(&white, &block(1001), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&black, &block(1001), CwChessAction::AcceptTakeback)
+            .unwrap();
+        // inactivity is measured from when the takeback was accepted
+        match game
+            .claim_abandonment(&black, &block(1002), 100)
+            .unwrap_err()
+        {
+            ContractError::GameNotAbandoned { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(game.claim_abandonment(&black, &block(1101), 100).is_err());
+        assert_eq!(
+            game.claim_abandonment(&black, &block(1102), 100).unwrap(),
+            &Some(CwChessGameOver::WhiteAbandons)
+        );
+    }
+
+    #[test]
+    fn test_takeback_move_limit() {
+        let mut game = fen_game(START);
+        game.move_limit = Some(100);
+        let white = Addr::unchecked("white");
+        let black = Addr::unchecked("black");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        game.make_move(&black, &block(10), "e5".into()).unwrap();
+        game.make_move(&white, &block(100), "Nf3".into()).unwrap();
+        game.make_move(&white, &block(150), CwChessAction::RequestTakeback)
+            .unwrap();
+        game.make_move(&black, &block(150), CwChessAction::AcceptTakeback)
+            .unwrap();
+        // white's move limit restarts when takeback is accepted
+        assert_eq!(
+            game.get_remaining_times(&block(150)),
+            (Some(100), Some(100))
+        );
+        assert!(!game.is_flagged(&block(250)));
+        assert!(game.is_flagged(&block(251)));
+        game.make_move(&white, &block(250), "Nc3".into()).unwrap();
+        assert_eq!(game.status, None);
+        // later moves are measured from the previous move
+        assert!(game.is_flagged(&block(351)));
+    }
+
+    #[test]
+    fn test_takeback_wager() {
+        let mut game = fen_game(START);
+        game.wager = Some(Wager::Native(coin(100, "ujuno")));
+        let white = Addr::unchecked("white");
+
+        game.make_move(&white, &block(0), "e4".into()).unwrap();
+        assert!(game.undo_fens.is_empty());
+        match game
+            .make_move(&white, &block(0), CwChessAction::RequestTakeback)
+            .unwrap_err()
+        {
+            ContractError::TakebackNotAllowed { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_category() {
+        let category = |block_limit: Option<u64>, increment: Option<u64>, clock: CwChessClock| {
+            CwChessCategory::from(&CwChessTimeControl {
+                block_limit,
+                clock,
+                increment_blocks: increment,
+                ..CwChessTimeControl::default()
+            })
+        };
+        let seconds = CwChessClock::Seconds;
+        assert_eq!(
+            category(Some(60), None, seconds.clone()),
+            CwChessCategory::Bullet
+        );
+        // 2+1 is estimated as 2 minutes plus 40 increments
+        assert_eq!(
+            category(Some(120), Some(1), seconds.clone()),
+            CwChessCategory::Bullet
+        );
+        assert_eq!(
+            category(Some(180), Some(2), seconds.clone()),
+            CwChessCategory::Blitz
+        );
+        assert_eq!(
+            category(Some(600), None, seconds.clone()),
+            CwChessCategory::Rapid
+        );
+        assert_eq!(
+            category(Some(1800), None, seconds.clone()),
+            CwChessCategory::Classical
+        );
+        assert_eq!(
+            category(Some(86400), None, seconds),
+            CwChessCategory::Correspondence
+        );
+        assert_eq!(
+            category(None, None, CwChessClock::Seconds),
+            CwChessCategory::Correspondence
+        );
+        // blocks are estimated as 6 seconds
+        assert_eq!(
+            category(Some(100), None, CwChessClock::Blocks),
+            CwChessCategory::Rapid
+        );
+        // move_limit is estimated as 40 moves using all of the limit
+        let move_limit = |block_limit: Option<u64>, move_limit: u64| {
+            CwChessCategory::from(&CwChessTimeControl {
+                block_limit,
+                clock: CwChessClock::Seconds,
+                move_limit: Some(move_limit),
+                ..CwChessTimeControl::default()
+            })
+        };
+        assert_eq!(move_limit(None, 4), CwChessCategory::Bullet);
+        assert_eq!(move_limit(None, 10), CwChessCategory::Blitz);
+        assert_eq!(move_limit(None, 1800), CwChessCategory::Classical);
+        assert_eq!(move_limit(None, 86400), CwChessCategory::Correspondence);
+        // shorter of block_limit and move_limit
+        assert_eq!(move_limit(Some(86400), 10), CwChessCategory::Blitz);
+        assert_eq!(move_limit(Some(60), 3600), CwChessCategory::Bullet);
+
+        // games created before categories are classified from time control
+        let mut game = fen_game(START);
+        game.block_limit = Some(50);
+        assert_eq!(game.get_category(), CwChessCategory::Blitz);
+        game.category = Some(CwChessCategory::Bullet);
+        assert_eq!(game.get_category(), CwChessCategory::Bullet);
+    }
+}
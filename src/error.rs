@@ -10,22 +10,44 @@ pub enum ContractError {
 
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+    #[error("cannot abort after first move")]
+    CannotAbort {},
+    #[error("cannot claim draw")]
+    CannotClaimDraw {},
     #[error("cannot play self")]
     CannotPlaySelf {},
+    #[error("challenge expired")]
+    ChallengeExpired {},
     #[error("challenge not found")]
     ChallengeNotFound {},
+    #[error("game not abandoned")]
+    GameNotAbandoned {},
     #[error("game already over")]
     GameAlreadyOver {},
     #[error("game not found")]
     GameNotFound {},
+    #[error("game not over")]
+    GameNotOver {},
     #[error("game not timed out")]
     GameNotTimedOut {},
     #[error("invalid move")]
     InvalidMove {},
     #[error("invalid position")]
     InvalidPosition {},
+    #[error("invalid time control")]
+    InvalidTimeControl {},
+    #[error("invalid wager")]
+    InvalidWager {},
     #[error("not your challenge")]
     NotYourChallenge {},
     #[error("not your turn")]
     NotYourTurn {},
+    #[error("rematch already requested")]
+    RematchRequested {},
+    #[error("seek not found")]
+    SeekNotFound {},
+    #[error("takeback not allowed")]
+    TakebackNotAllowed {},
+    #[error("wager does not match challenge")]
+    WagerMismatch {},
 }
@@ -0,0 +1,137 @@
+use crate::cwchess::CwChessColor;
+
+// Helpers for reading FEN strings.
+//
+// chess-engine loads and saves positions as FEN, but does not track position history
+// or the halfmove clock, so draw rules are checked using the FEN strings directly.
+
+// FNV-1a offset basis and prime (64 bit)
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// get castling rights
+pub fn castling(fen: &str) -> &str {
+    fen.split(' ').nth(2).unwrap_or("-")
+}
+
+// get (halfmove clock, fullmove number), defaults are used when fields are missing
+// None if either field is not a valid number
+pub fn clocks(fen: &str) -> Option<(u16, u32)> {
+    let mut fields = fen.split(' ').skip(4);
+    let halfmove_clock = match fields.next() {
+        Some(clock) => clock.parse().ok()?,
+        None => 0,
+    };
+    let fullmove_number = match fields.next() {
+        Some(number) => number.parse().ok().filter(|number| *number > 0)?,
+        None => 1,
+    };
+    Some((halfmove_clock, fullmove_number))
+}
+
+// get fullmove number (starts at 1, incremented after black moves)
+pub fn fullmove_number(fen: &str) -> u32 {
+    clocks(fen).map_or(1, |clocks| clocks.1)
+}
+
+// get halfmove clock (plies since last capture or pawn move)
+pub fn halfmove_clock(fen: &str) -> u16 {
+    clocks(fen).map_or(0, |clocks| clocks.0)
+}
+
+// check whether a move captured a piece or moved a pawn
+pub fn is_irreversible(before: &str, after: &str) -> bool {
+    let before = pieces(before);
+    let after = pieces(after);
+    // capture removes a piece
+    if before.len() != after.len() {
+        return true;
+    }
+    // pawn move changes pawn squares
+    let pawns = |pieces: &Vec<(usize, char)>| -> Vec<(usize, char)> {
+        pieces
+            .iter()
+            .filter(|(_, piece)| *piece == 'P' || *piece == 'p')
+            .copied()
+            .collect()
+    };
+    pawns(&before) != pawns(&after)
+}
+
+// check whether neither player has enough material to checkmate
+// (only kings, one knight or bishop, or only bishops on same color squares)
+pub fn is_insufficient_material(fen: &str) -> bool {
+    let pieces = pieces(fen)
+        .into_iter()
+        .filter(|(_, piece)| *piece != 'K' && *piece != 'k')
+        .collect::<Vec<_>>();
+    match pieces.as_slice() {
+        [] => true,
+        [(_, piece)] => "BbNn".contains(*piece),
+        _ => {
+            let square_color = |square: &usize| (square / 8 + square % 8) % 2;
+            let first_color = square_color(&pieces[0].0);
+            pieces.iter().all(|(square, piece)| {
+                (*piece == 'B' || *piece == 'b') && square_color(square) == first_color
+            })
+        }
+    }
+}
+
+// get (square, piece) for each piece on the board
+// squares are numbered 0 (a8) through 63 (h1), in FEN order
+pub fn pieces(fen: &str) -> Vec<(usize, char)> {
+    let placement = fen.split(' ').next().unwrap_or_default();
+    let mut pieces = vec![];
+    let mut square: usize = 0;
+    for c in placement.chars() {
+        match c {
+            '/' => {}
+            '1'..='8' => square += c.to_digit(10).unwrap_or_default() as usize,
+            _ => {
+                pieces.push((square, c));
+                square += 1;
+            }
+        }
+    }
+    pieces
+}
+
+// hash of the parts of a FEN that identify a position for repetition
+// (piece placement, side to move, castling rights and en passant square)
+pub fn position_key(fen: &str) -> u64 {
+    let position = fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+    position.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// get color to move
+pub fn turn_color(fen: &str) -> Option<CwChessColor> {
+    match fen.split(' ').nth(1) {
+        Some("w") => Some(CwChessColor::White),
+        Some("b") => Some(CwChessColor::Black),
+        _ => None,
+    }
+}
+
+// replace castling rights
+pub fn with_castling(fen: &str, castling: &str) -> String {
+    with_field(fen, 2, castling)
+}
+
+// replace halfmove clock and fullmove number
+pub fn with_clocks(fen: &str, halfmove_clock: u16, fullmove_number: u32) -> String {
+    let fen = with_field(fen, 4, &halfmove_clock.to_string());
+    with_field(&fen, 5, &fullmove_number.to_string())
+}
+
+fn with_field(fen: &str, index: usize, value: &str) -> String {
+    let mut fields = fen.split(' ').collect::<Vec<_>>();
+    // add missing clock fields
+    while fields.len() <= index {
+        fields.push(if fields.len() == 5 { "1" } else { "0" });
+    }
+    fields[index] = value;
+    fields.join(" ")
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use crate::cwchess::CwChessColor;
+    use crate::fen::{
+        clocks, fullmove_number, halfmove_clock, is_insufficient_material, is_irreversible, pieces,
+        position_key, turn_color,
+    };
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_clocks() {
+        assert_eq!(clocks(START), Some((0, 1)));
+        assert_eq!(
+            clocks("8/8/8/4k3/8/8/8/4K3 w - - 300 1000"),
+            Some((300, 1000))
+        );
+        // missing clocks
+        assert_eq!(clocks("8/8/8/4k3/8/8/8/4K3 w - -"), Some((0, 1)));
+        assert_eq!(clocks("8/8/8/4k3/8/8/8/4K3 w - - 5"), Some((5, 1)));
+        // invalid clocks
+        assert_eq!(clocks("8/8/8/4k3/8/8/8/4K3 w - - x 1"), None);
+        assert_eq!(clocks("8/8/8/4k3/8/8/8/4K3 w - - 0 -1"), None);
+        assert_eq!(clocks("8/8/8/4k3/8/8/8/4K3 w - - 0 0"), None);
+    }
+
+    #[test]
+    fn test_fullmove_number() {
+        assert_eq!(fullmove_number(START), 1);
+        assert_eq!(fullmove_number("8/8/8/4k3/8/8/8/4K3 w - - 42 80"), 80);
+        assert_eq!(fullmove_number("8/8/8/4k3/8/8/8/4K3 w - - 42 300"), 300);
+    }
+
+    #[test]
+    fn test_halfmove_clock() {
+        assert_eq!(halfmove_clock(START), 0);
+        assert_eq!(halfmove_clock("8/8/8/4k3/8/8/8/4K3 w - - 42 80"), 42);
+        // missing clock
+        assert_eq!(halfmove_clock("8/8/8/4k3/8/8/8/4K3 w - -"), 0);
+    }
+
+    #[test]
+    fn test_is_irreversible() {
+        // knight move
+        assert!(!is_irreversible(
+            START,
+            "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1"
+        ));
+        // pawn move
+        assert!(is_irreversible(
+            START,
+            "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1"
+        ));
+        // capture
+        assert!(is_irreversible(
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2"
+        ));
+    }
+
+    #[test]
+    fn test_is_insufficient_material() {
+        assert!(!is_insufficient_material(START));
+        // king vs king
+        assert!(is_insufficient_material("8/8/8/4k3/8/8/8/4K3 w - - 0 1"));
+        // king and bishop vs king
+        assert!(is_insufficient_material("8/8/8/4k3/8/8/8/2B1K3 w - - 0 1"));
+        // king vs king and knight
+        assert!(is_insufficient_material("8/8/8/4k3/8/8/8/4K1n1 w - - 0 1"));
+        // bishops on same color squares (c1 and f8 are both dark)
+        assert!(is_insufficient_material(
+            "5b2/8/8/4k3/8/8/8/2B1K3 w - - 0 1"
+        ));
+        // bishops on opposite color squares
+        assert!(!is_insufficient_material(
+            "2b5/8/8/4k3/8/8/8/2B1K3 w - - 0 1"
+        ));
+        // two knights (mate is possible with help)
+        assert!(!is_insufficient_material(
+            "8/8/8/4k3/8/8/8/1NN1K3 w - - 0 1"
+        ));
+        // knight vs bishop
+        assert!(!is_insufficient_material(
+            "8/8/8/4k3/8/8/8/2B1K1n1 w - - 0 1"
+        ));
+        // pawn, rook or queen
+        assert!(!is_insufficient_material("8/8/8/4k3/8/8/P7/4K3 w - - 0 1"));
+        assert!(!is_insufficient_material("8/8/8/4k3/8/8/8/R3K3 w - - 0 1"));
+        assert!(!is_insufficient_material("8/8/8/4k3/8/8/8/3qK3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_pieces() {
+        assert_eq!(
+            pieces("k7/8/8/8/8/8/8/6NK w - - 0 1"),
+            vec![(0, 'k'), (62, 'N'), (63, 'K')]
+        );
+    }
+
+    #[test]
+    fn test_position_key() {
+        // clocks are ignored
+        assert_eq!(
+            position_key(START),
+            position_key("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 12 7")
+        );
+        // side to move is not ignored
+        assert_ne!(
+            position_key(START),
+            position_key("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+        );
+        // castling rights are not ignored
+        assert_ne!(
+            position_key(START),
+            position_key("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1")
+        );
+    }
+
+    #[test]
+    fn test_turn_color() {
+        assert_eq!(turn_color(START), Some(CwChessColor::White));
+        assert_eq!(
+            turn_color("8/8/8/4k3/8/8/8/4K3 b - - 42 80"),
+            Some(CwChessColor::Black)
+        );
+        assert_eq!(turn_color("8/8/8/4k3/8/8/8/4K3"), None);
+    }
+}
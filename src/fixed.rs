@@ -0,0 +1,107 @@
+// Deterministic fixed-point arithmetic for rating calculations.
+//
+// Floating point operations are not allowed in CosmWasm contracts, and results must be
+// identical on every validator, so values are stored as i128 multiplied by SCALE.
+
+pub const SCALE: i128 = 1_000_000_000_000;
+// ln(2) * SCALE
+pub const LN_2: i128 = 693_147_180_560;
+// ln(10) * SCALE
+pub const LN_10: i128 = 2_302_585_092_994;
+
+pub fn from_int(value: i128) -> i128 {
+    value * SCALE
+}
+
+// round to nearest integer, halves away from zero
+pub fn to_int(value: i128) -> i128 {
+    div_round(value, SCALE)
+}
+
+pub fn mul(a: i128, b: i128) -> i128 {
+    div_round(a * b, SCALE)
+}
+
+pub fn div(a: i128, b: i128) -> i128 {
+    div_round(a * SCALE, b)
+}
+
+// e^x
+pub fn exp(x: i128) -> i128 {
+    // e^x = 2^k * e^r, where |r| <= ln(2) / 2
+    let k = div_round(x, LN_2);
+    let r = x - k * LN_2;
+    // taylor series converges quickly for small r
+    let mut sum = SCALE;
+    let mut term = SCALE;
+    for i in 1..30 {
+        term = div_round(mul(term, r), i);
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    if k >= 0 {
+        sum << k
+    } else {
+        sum >> -k
+    }
+}
+
+// natural logarithm, x must be positive
+pub fn ln(x: i128) -> i128 {
+    // ln(x) = k * ln(2) + ln(m), where 1 <= m < 2
+    let mut k = 0;
+    let mut m = x;
+    while m >= 2 * SCALE {
+        m = div_round(m, 2);
+        k += 1;
+    }
+    while m < SCALE {
+        m *= 2;
+        k -= 1;
+    }
+    // ln(m) = 2 * atanh(y) = 2 * (y + y^3 / 3 + y^5 / 5 + ...), where y = (m - 1) / (m + 1)
+    let y = div(m - SCALE, m + SCALE);
+    let y_squared = mul(y, y);
+    let mut sum = 0;
+    let mut term = y;
+    let mut i = 1;
+    while term != 0 {
+        sum += div_round(term, i);
+        term = mul(term, y_squared);
+        i += 2;
+    }
+    k * LN_2 + 2 * sum
+}
+
+// square root, rounded down
+pub fn sqrt(x: i128) -> i128 {
+    if x <= 0 {
+        return 0;
+    }
+    // integer square root of x * SCALE, using newton's method
+    let n = x * SCALE;
+    let mut root = n;
+    let mut next = (n + 1) / 2;
+    while next < root {
+        root = next;
+        next = (n / next + next) / 2;
+    }
+    root
+}
+
+// integer division rounding halves away from zero
+fn div_round(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    let remainder = a % b;
+    if 2 * remainder.abs() >= b.abs() {
+        if (a < 0) == (b < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        }
+    } else {
+        quotient
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use crate::fixed::{div, exp, from_int, ln, mul, sqrt, to_int, LN_10, LN_2, SCALE};
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(mul(from_int(3), SCALE / 2), 1_500_000_000_000);
+        assert_eq!(div(from_int(1), from_int(3)), 333_333_333_333);
+        assert_eq!(div(from_int(2), from_int(3)), 666_666_666_667);
+        assert_eq!(to_int(2_500_000_000_000), 3);
+        assert_eq!(to_int(-2_500_000_000_000), -3);
+        assert_eq!(to_int(2_499_999_999_999), 2);
+    }
+
+    #[test]
+    fn test_exp() {
+        assert_eq!(exp(0), SCALE);
+        assert_eq!(exp(LN_2), from_int(2));
+        // within 1e-9 of exact values
+        let close = |actual: i128, expected: i128| (actual - expected).abs() <= 1_000;
+        assert!(close(exp(SCALE), 2_718_281_828_459));
+        assert!(close(exp(-SCALE), 367_879_441_171));
+        assert!(close(exp(LN_10), from_int(10)));
+        assert!(close(exp(LN_10 * 2), from_int(100)));
+    }
+
+    #[test]
+    fn test_ln() {
+        assert_eq!(ln(SCALE), 0);
+        let close = |actual: i128, expected: i128| (actual - expected).abs() <= 1_000;
+        assert!(close(ln(from_int(2)), LN_2));
+        assert!(close(ln(from_int(10)), LN_10));
+        assert!(close(ln(SCALE / 100), -2 * LN_10));
+        assert!(close(ln(exp(from_int(5))), from_int(5)));
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(sqrt(0), 0);
+        assert_eq!(sqrt(from_int(4)), from_int(2));
+        assert_eq!(sqrt(SCALE / 4), SCALE / 2);
+        assert_eq!(sqrt(from_int(2)), 1_414_213_562_373);
+    }
+}
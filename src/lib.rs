@@ -1,9 +1,21 @@
+pub mod board;
+mod board_test;
+pub mod chess960;
+mod chess960_test;
 pub mod contract;
 mod contract_test;
 pub mod cwchess;
+mod cwchess_test;
 mod error;
+pub mod fen;
+mod fen_test;
+pub mod fixed;
+mod fixed_test;
 pub mod msg;
+pub mod rating;
+mod rating_test;
 pub mod state;
 mod state_test;
+pub mod wager;
 
 pub use crate::error::ContractError;
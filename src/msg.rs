@@ -1,37 +1,153 @@
+use cosmwasm_std::BlockInfo;
+use cw20::{Cw20ReceiveMsg, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+use crate::cwchess::{
+    CwChessAction, CwChessCategory, CwChessClock, CwChessColor, CwChessGame, CwChessGameOver,
+    CwChessTimeControl, CwChessVariant,
+};
+use crate::rating::RatingSystem;
+use crate::state::PlayerRating;
+use crate::wager::Wager;
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    // cw20 token contracts accepted for wagers, other tokens are refused
+    pub cw20_tokens: Option<Vec<String>>,
+    // blocks without a move before waiting player can claim win by abandonment
+    // only for games without block_limit or move_limit, None for no limit
+    pub inactivity_blocks: Option<u64>,
+    // how rated games update player ratings, default is Elo
+    pub rating_system: Option<RatingSystem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateChallengeMsg {
+    // per player time limit, in blocks or seconds depending on clock
+    pub block_limit: Option<u64>,
+    // default is blocks
+    pub clock: Option<CwChessClock>,
+    // time at start of each move that doesn't count toward block_limit
+    pub delay_blocks: Option<u64>,
+    // height or time after which challenge can't be accepted
+    pub expires_at: Option<Expiration>,
+    // time allowed for each player's first move, game is aborted if exceeded
+    pub first_move_limit: Option<u64>,
+    // time added to block_limit after each move
+    pub increment_blocks: Option<u64>,
+    // time allowed for each move, for correspondence games
+    pub move_limit: Option<u64>,
+    pub opponent: Option<String>,
+    pub play_as: Option<CwChessColor>,
+    // update player ratings when game ends, default false
+    pub rated: Option<bool>,
+    // starting position in FEN, default is standard starting position
+    pub start_fen: Option<String>,
+    // default is standard chess
+    pub variant: Option<CwChessVariant>,
+}
+
+impl CreateChallengeMsg {
+    pub fn get_time_control(&self) -> CwChessTimeControl {
+        CwChessTimeControl {
+            block_limit: self.block_limit,
+            clock: self.clock.clone().unwrap_or_default(),
+            delay_blocks: self.delay_blocks,
+            first_move_limit: self.first_move_limit,
+            increment_blocks: self.increment_blocks,
+            move_limit: self.move_limit,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SeekMsg {
+    // color preference, None for either color
+    pub play_as: Option<CwChessColor>,
+    pub rated: bool,
+    // maximum rating difference from opponent, None for any opponent
+    pub rating_range: Option<u32>,
+    pub time_control: CwChessTimeControl,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    CreateChallenge {
-        block_limit: Option<u64>,
-        opponent: Option<String>,
-        play_as: Option<CwChessColor>,
-        // sender is creator
-    },
+    // sender is creator
+    // funds are wager
+    CreateChallenge(CreateChallengeMsg),
+    // cw20 token wager, msg is a ReceiveMsg
+    Receive(Cw20ReceiveMsg),
     AcceptChallenge {
         challenge_id: u64,
         // sender is player
+        // funds must match challenge wager
     },
     CancelChallenge {
         challenge_id: u64,
         // sender is creator
+        // wager is refunded
+    },
+    DeclineChallenge {
+        challenge_id: u64,
+        // sender is opponent
+        // wager is refunded to creator
+    },
+    // waiting player claims win when opponent has not moved within inactivity_blocks
+    ClaimAbandonment {
+        game_id: u64,
     },
     DeclareTimeout {
         game_id: u64,
     },
+    // challenge former opponent with same time control and reversed colors
+    // game starts if opponent already requested rematch
+    // funds are wager, and must match opponent's rematch wager
+    Rematch {
+        game_id: u64,
+    },
+    // pair with oldest compatible seek, or wait for a match
+    // funds are wager, and must match seek
+    Seek(SeekMsg),
+    // anyone can remove expired challenges
+    // wagers are refunded to creators
+    PruneChallenges {
+        // default and maximum 25
+        limit: Option<u32>,
+    },
     Turn {
         game_id: u64,
         action: CwChessAction,
         // sender is player
         // block is timestamp
     },
+    WithdrawSeek {
+        seek_id: u64,
+        // sender is seeker
+        // wager is refunded
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    // sender is creator
+    // tokens are wager
+    CreateChallenge(CreateChallengeMsg),
+    AcceptChallenge {
+        challenge_id: u64,
+        // sender is player
+        // tokens must match challenge wager
+    },
+    // tokens are wager, and must match opponent's rematch wager
+    Rematch {
+        game_id: u64,
+    },
+    // tokens are wager
+    Seek(SeekMsg),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,8 +158,13 @@ pub enum QueryMsg {
     },
     GetChallenges {
         after: Option<u64>,
+        // include expired challenges, default false
+        expired: Option<bool>,
         player: Option<String>,
     },
+    GetClock {
+        game_id: u64,
+    },
     GetGame {
         game_id: u64,
     },
@@ -52,6 +173,60 @@ pub enum QueryMsg {
         game_over: Option<bool>,
         player: Option<String>,
     },
+    // players ordered by rating, highest first
+    GetLeaderboard {
+        category: CwChessCategory,
+        // only players whose rating is not provisional (see RatingSummary), default false
+        // this is not a number of games, Glicko-2 uses rating deviation
+        established: Option<bool>,
+        // next from previous page
+        start_after: Option<LeaderboardCursor>,
+        // default and maximum 25
+        limit: Option<u32>,
+    },
+    GetPlayer {
+        address: String,
+    },
+    GetPlayerStats {
+        address: String,
+    },
+    GetSeeks {
+        after: Option<u64>,
+    },
+}
+
+// clock at current block, measured in blocks or seconds depending on clock
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameClock {
+    // None if game has no time limit
+    pub black_remaining: Option<u64>,
+    pub black_used: u64,
+    pub clock: CwChessClock,
+    // player to move has run out of time, and game can be ended with DeclareTimeout
+    pub flagged: bool,
+    pub game_id: u64,
+    pub turn_color: Option<CwChessColor>,
+    // None if game has no time limit
+    pub white_remaining: Option<u64>,
+    pub white_used: u64,
+}
+
+impl GameClock {
+    pub fn new(game: &CwChessGame, block: &BlockInfo) -> GameClock {
+        let (white_used, black_used) = game.get_used_times(block);
+        let (white_remaining, black_remaining) = game.get_remaining_times(block);
+        GameClock {
+            black_remaining,
+            black_used,
+            clock: game.clock.clone(),
+            flagged: game.is_flagged(block),
+            game_id: game.game_id,
+            turn_color: game.turn_color(),
+            white_remaining,
+            white_used,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -59,11 +234,23 @@ pub enum QueryMsg {
 pub struct GameSummary {
     pub block_limit: Option<u64>,
     pub block_start: u64,
+    pub category: CwChessCategory,
+    pub chess960_position: Option<u16>,
+    pub clock: CwChessClock,
+    pub delay_blocks: Option<u64>,
+    pub first_move_limit: Option<u64>,
     pub game_id: u64,
+    pub increment_blocks: Option<u64>,
+    pub move_limit: Option<u64>,
     pub player1: String,
     pub player2: String,
+    pub rated: bool,
+    pub rematch_of: Option<u64>,
+    pub rematched_by: Option<u64>,
     pub status: Option<CwChessGameOver>,
     pub turn_color: Option<CwChessColor>,
+    pub variant: CwChessVariant,
+    pub wager: Option<Wager>,
 }
 
 impl From<&CwChessGame> for GameSummary {
@@ -71,11 +258,81 @@ impl From<&CwChessGame> for GameSummary {
         GameSummary {
             block_limit: game.block_limit,
             block_start: game.block_start,
+            category: game.get_category(),
+            chess960_position: game.chess960_position,
+            clock: game.clock.clone(),
+            delay_blocks: game.delay_blocks,
+            first_move_limit: game.first_move_limit,
             game_id: game.game_id,
+            increment_blocks: game.increment_blocks,
+            move_limit: game.move_limit,
             player1: game.player1.to_string(),
             player2: game.player2.to_string(),
+            rated: game.rated,
+            rematch_of: game.rematch_of,
+            rematched_by: game.rematched_by,
             status: game.status.clone(),
             turn_color: game.turn_color(),
+            variant: game.variant.clone(),
+            wager: game.wager.clone(),
+        }
+    }
+}
+
+// position in leaderboard, after player with rating
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LeaderboardCursor {
+    pub address: String,
+    pub rating: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LeaderboardPage {
+    // start_after for next page, None after last page
+    pub next: Option<LeaderboardCursor>,
+    pub ratings: Vec<RatingSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerSummary {
+    pub address: String,
+    // rating in each category
+    pub ratings: Vec<RatingSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RatingSummary {
+    pub address: String,
+    pub category: CwChessCategory,
+    // Glicko-2 rating deviation, None when using Elo
+    pub deviation: Option<u32>,
+    pub draws: u64,
+    // rated games played
+    pub games: u64,
+    pub losses: u64,
+    // rating is less reliable, Elo players with fewer than 20 rated games,
+    // or Glicko-2 players with deviation above 110
+    pub provisional: bool,
+    pub rating: u32,
+    pub wins: u64,
+}
+
+impl From<&PlayerRating> for RatingSummary {
+    fn from(rating: &PlayerRating) -> RatingSummary {
+        RatingSummary {
+            address: rating.address.to_string(),
+            category: rating.category.clone(),
+            deviation: rating.glicko2.as_ref().map(|glicko2| glicko2.deviation),
+            draws: rating.draws,
+            games: rating.games,
+            losses: rating.losses,
+            provisional: rating.is_provisional(),
+            rating: rating.rating,
+            wins: rating.wins,
         }
     }
 }
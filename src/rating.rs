@@ -0,0 +1,278 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cwchess::{CwChessColor, CwChessGameOver};
+use crate::fixed::{self, SCALE};
+use crate::state::PlayerRating;
+
+pub const INITIAL_RATING: u32 = 1500;
+// Elo players are provisional until they have played this many rated games
+pub const PROVISIONAL_GAMES: u64 = 20;
+// Glicko-2 players are provisional while deviation is above this
+pub const PROVISIONAL_DEVIATION: u32 = 110;
+// rating change for each point scored above or below expected score
+const K_FACTOR: i128 = 20;
+const K_FACTOR_PROVISIONAL: i128 = 40;
+// larger rating differences are treated as this difference
+const MAX_RATING_DIFFERENCE: i128 = 800;
+
+// Glicko-2 constants, fixed-point
+// ratings are divided by this to convert to Glicko-2 scale
+const GLICKO2_SCALE: i128 = 173_717_800_000_000;
+const GLICKO2_INITIAL_DEVIATION: u32 = 350;
+const GLICKO2_INITIAL_VOLATILITY: u64 = 60_000_000_000;
+// constrains change in volatility over time
+const GLICKO2_TAU: i128 = SCALE / 2;
+// convergence tolerance and iteration limit when calculating volatility
+const GLICKO2_EPSILON: i128 = 1_000_000;
+const GLICKO2_ITERATIONS: usize = 100;
+const PI_SQUARED: i128 = 9_869_604_401_089;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingSystem {
+    Elo,
+    Glicko2 {
+        // deviation grows for each period of blocks without a rated game
+        rating_period_blocks: u64,
+    },
+}
+
+// #[default] variant attribute isn't available on the pinned toolchain
+#[allow(clippy::derivable_impls)]
+impl Default for RatingSystem {
+    fn default() -> Self {
+        RatingSystem::Elo
+    }
+}
+
+// Glicko-2 values stored with player rating
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Glicko2 {
+    // rating deviation, in rating points
+    pub deviation: u32,
+    // block height of last rated game
+    pub last_block: u64,
+    // fixed-point volatility, divided by 10^12
+    pub volatility: u64,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Glicko2 {
+            deviation: GLICKO2_INITIAL_DEVIATION,
+            last_block: 0,
+            volatility: GLICKO2_INITIAL_VOLATILITY,
+        }
+    }
+}
+
+// score for player, fixed-point 1 for a win, 1/2 for a draw, and 0 for a loss
+// None for aborted games, which are not rated
+pub fn score(status: &CwChessGameOver, color: &CwChessColor) -> Option<i128> {
+    if *status == CwChessGameOver::Aborted {
+        return None;
+    }
+    Some(match status.winner() {
+        Some(winner) if winner == *color => SCALE,
+        Some(_) => 0,
+        None => SCALE / 2,
+    })
+}
+
+// update ratings of both players after a rated game
+pub fn update_ratings(
+    white: &mut PlayerRating,
+    black: &mut PlayerRating,
+    status: &CwChessGameOver,
+    rating_system: &RatingSystem,
+    block_height: u64,
+) {
+    let white_score = match score(status, &CwChessColor::White) {
+        Some(score) => score,
+        None => return,
+    };
+    let black_score = SCALE - white_score;
+    match rating_system {
+        RatingSystem::Elo => {
+            let white_rating = elo_rating(white, black.rating, white_score);
+            let black_rating = elo_rating(black, white.rating, black_score);
+            white.rating = white_rating;
+            black.rating = black_rating;
+        }
+        RatingSystem::Glicko2 {
+            rating_period_blocks,
+        } => {
+            let (white_rating, white_glicko2) = glicko2_rating(
+                white,
+                black,
+                white_score,
+                block_height,
+                *rating_period_blocks,
+            );
+            let (black_rating, black_glicko2) = glicko2_rating(
+                black,
+                white,
+                black_score,
+                block_height,
+                *rating_period_blocks,
+            );
+            white.rating = white_rating;
+            white.glicko2 = Some(white_glicko2);
+            black.rating = black_rating;
+            black.glicko2 = Some(black_glicko2);
+        }
+    }
+    for (player, score) in [(white, white_score), (black, black_score)] {
+        player.games += 1;
+        match score {
+            SCALE => player.wins += 1,
+            0 => player.losses += 1,
+            _ => player.draws += 1,
+        }
+    }
+}
+
+// expected score for player, fixed-point between 0 and 1
+pub fn elo_expected_score(rating: u32, opponent_rating: u32) -> i128 {
+    // 1 / (1 + 10^(difference / 400))
+    let power = fixed::exp(fixed::LN_10 * rating_difference(opponent_rating, rating) / 400);
+    fixed::div(SCALE, SCALE + power)
+}
+
+// new rating for player after scoring against opponent
+pub fn elo_rating(player: &PlayerRating, opponent_rating: u32, score: i128) -> u32 {
+    let k_factor = if player.games < PROVISIONAL_GAMES {
+        K_FACTOR_PROVISIONAL
+    } else {
+        K_FACTOR
+    };
+    let expected = elo_expected_score(player.rating, opponent_rating);
+    let change = fixed::to_int(k_factor * (score - expected));
+    (player.rating as i128 + change).max(0) as u32
+}
+
+// new rating and Glicko-2 values for player after scoring against opponent
+//
+// each game is treated as a rating period with one game, after increasing deviation
+// for rating periods since each player's last rated game
+pub fn glicko2_rating(
+    player: &PlayerRating,
+    opponent: &PlayerRating,
+    score: i128,
+    block_height: u64,
+    rating_period_blocks: u64,
+) -> (u32, Glicko2) {
+    let glicko2 = player.glicko2.clone().unwrap_or_default();
+    let opponent_glicko2 = opponent.glicko2.clone().unwrap_or_default();
+    let volatility = glicko2.volatility as i128;
+    // convert to Glicko-2 scale
+    let difference = fixed::div(
+        fixed::from_int(rating_difference(player.rating, opponent.rating)),
+        GLICKO2_SCALE,
+    );
+    let phi = glicko2_deviation(&glicko2, block_height, rating_period_blocks);
+    let opponent_phi = glicko2_deviation(&opponent_glicko2, block_height, rating_period_blocks);
+    // g(phi) = 1 / sqrt(1 + 3 * phi^2 / pi^2)
+    let g = fixed::div(
+        SCALE,
+        fixed::sqrt(SCALE + fixed::div(3 * fixed::mul(opponent_phi, opponent_phi), PI_SQUARED)),
+    );
+    let expected = fixed::div(SCALE, SCALE + fixed::exp(-fixed::mul(g, difference)));
+    // estimated variance and improvement based on game outcome
+    let variance = fixed::div(
+        SCALE,
+        fixed::mul(fixed::mul(fixed::mul(g, g), expected), SCALE - expected),
+    );
+    let improvement = fixed::mul(fixed::mul(variance, g), score - expected);
+    let volatility = glicko2_volatility(phi, volatility, variance, improvement);
+    // update deviation and rating
+    let phi_star = fixed::sqrt(fixed::mul(phi, phi) + fixed::mul(volatility, volatility));
+    let phi = fixed::div(
+        SCALE,
+        fixed::sqrt(
+            fixed::div(SCALE, fixed::mul(phi_star, phi_star)) + fixed::div(SCALE, variance),
+        ),
+    );
+    let change = fixed::mul(fixed::mul(fixed::mul(phi, phi), g), score - expected);
+    let rating = player.rating as i128 + fixed::to_int(fixed::mul(change, GLICKO2_SCALE));
+    let deviation = fixed::to_int(fixed::mul(phi, GLICKO2_SCALE));
+    (
+        rating.max(0) as u32,
+        Glicko2 {
+            deviation: deviation.min(GLICKO2_INITIAL_DEVIATION as i128) as u32,
+            last_block: block_height,
+            volatility: volatility as u64,
+        },
+    )
+}
+
+// deviation on Glicko-2 scale, increased for rating periods since last rated game
+fn glicko2_deviation(glicko2: &Glicko2, block_height: u64, rating_period_blocks: u64) -> i128 {
+    let phi = fixed::div(fixed::from_int(glicko2.deviation as i128), GLICKO2_SCALE);
+    if glicko2.last_block == 0 || rating_period_blocks == 0 {
+        return phi;
+    }
+    let periods = fixed::div(
+        fixed::from_int(block_height.saturating_sub(glicko2.last_block) as i128),
+        fixed::from_int(rating_period_blocks as i128),
+    );
+    let volatility = glicko2.volatility as i128;
+    let phi =
+        fixed::sqrt(fixed::mul(phi, phi) + fixed::mul(fixed::mul(volatility, volatility), periods));
+    let max_phi = fixed::div(
+        fixed::from_int(GLICKO2_INITIAL_DEVIATION as i128),
+        GLICKO2_SCALE,
+    );
+    phi.min(max_phi)
+}
+
+// new volatility, using the Illinois algorithm from the Glicko-2 paper
+fn glicko2_volatility(phi: i128, volatility: i128, variance: i128, improvement: i128) -> i128 {
+    let phi_squared = fixed::mul(phi, phi);
+    let improvement_squared = fixed::mul(improvement, improvement);
+    let tau_squared = fixed::mul(GLICKO2_TAU, GLICKO2_TAU);
+    let a = fixed::ln(fixed::mul(volatility, volatility));
+    let f = |x: i128| -> i128 {
+        let e_x = fixed::exp(x);
+        let denominator = phi_squared + variance + e_x;
+        fixed::div(
+            fixed::mul(e_x, improvement_squared - phi_squared - variance - e_x),
+            2 * fixed::mul(denominator, denominator),
+        ) - fixed::div(x - a, tau_squared)
+    };
+    let mut lower = a;
+    let mut upper = if improvement_squared > phi_squared + variance {
+        fixed::ln(improvement_squared - phi_squared - variance)
+    } else {
+        let mut k = 1;
+        while k < GLICKO2_ITERATIONS as i128 && f(a - k * GLICKO2_TAU) < 0 {
+            k += 1;
+        }
+        a - k * GLICKO2_TAU
+    };
+    let mut f_lower = f(lower);
+    let mut f_upper = f(upper);
+    for _ in 0..GLICKO2_ITERATIONS {
+        if (upper - lower).abs() <= GLICKO2_EPSILON || f_upper == f_lower {
+            break;
+        }
+        let next = lower + fixed::div(fixed::mul(lower - upper, f_lower), f_upper - f_lower);
+        let f_next = f(next);
+        if (f_next < 0) != (f_upper < 0) || f_next == 0 {
+            lower = upper;
+            f_lower = f_upper;
+        } else {
+            f_lower /= 2;
+        }
+        upper = next;
+        f_upper = f_next;
+    }
+    fixed::exp(lower / 2)
+}
+
+// rating minus other rating, capped at MAX_RATING_DIFFERENCE
+fn rating_difference(rating: u32, other: u32) -> i128 {
+    (rating as i128 - other as i128).clamp(-MAX_RATING_DIFFERENCE, MAX_RATING_DIFFERENCE)
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use crate::cwchess::{CwChessCategory, CwChessColor, CwChessGameOver};
+    use crate::fixed::SCALE;
+    use crate::rating::{
+        elo_expected_score, glicko2_rating, score, update_ratings, Glicko2, RatingSystem,
+        INITIAL_RATING,
+    };
+    use crate::state::PlayerRating;
+
+    use cosmwasm_std::Addr;
+
+    #[test]
+    fn test_score() {
+        let white = CwChessColor::White;
+        assert_eq!(
+            score(&CwChessGameOver::WhiteCheckmates, &white),
+            Some(SCALE)
+        );
+        assert_eq!(score(&CwChessGameOver::WhiteTimeout, &white), Some(0));
+        assert_eq!(score(&CwChessGameOver::Stalemate, &white), Some(SCALE / 2));
+        assert_eq!(score(&CwChessGameOver::Aborted, &white), None);
+    }
+
+    #[test]
+    fn test_elo_expected_score() {
+        assert_eq!(elo_expected_score(1500, 1500), SCALE / 2);
+        // 400 points higher is expected to score 10 times as much as opponent
+        assert_eq!(elo_expected_score(1900, 1500), 909_090_909_091);
+        assert_eq!(elo_expected_score(1500, 1900), 90_909_090_909);
+        // differences over 800 are capped
+        assert_eq!(
+            elo_expected_score(1000, 2500),
+            elo_expected_score(1000, 1800)
+        );
+    }
+
+    #[test]
+    fn test_update_ratings() {
+        let mut white = PlayerRating::new(Addr::unchecked("white"), CwChessCategory::Blitz);
+        let mut black = PlayerRating::new(Addr::unchecked("black"), CwChessCategory::Blitz);
+        let elo = RatingSystem::Elo;
+        assert_eq!(white.rating, INITIAL_RATING);
+        assert!(white.is_provisional());
+
+        // provisional players change faster
+        update_ratings(
+            &mut white,
+            &mut black,
+            &CwChessGameOver::BlackResigns,
+            &elo,
+            1,
+        );
+        assert_eq!((white.rating, white.games, white.wins), (1520, 1, 1));
+        assert_eq!((black.rating, black.games), (1480, 1));
+
+        // established player
+        black.games = 20;
+        update_ratings(
+            &mut white,
+            &mut black,
+            &CwChessGameOver::DrawAccepted,
+            &elo,
+            2,
+        );
+        assert_eq!(white.rating, 1518);
+        assert_eq!(black.rating, 1481);
+        assert_eq!((black.draws, black.losses), (1, 1));
+
+        // aborted games are not rated
+        update_ratings(&mut white, &mut black, &CwChessGameOver::Aborted, &elo, 3);
+        assert_eq!((white.rating, white.games), (1518, 2));
+    }
+
+    #[test]
+    fn test_glicko2() {
+        let mut white = PlayerRating::new(Addr::unchecked("white"), CwChessCategory::Blitz);
+        let mut black = PlayerRating::new(Addr::unchecked("black"), CwChessCategory::Blitz);
+        let glicko2 = RatingSystem::Glicko2 {
+            rating_period_blocks: 1000,
+        };
+
+        // new players have large deviation, and ratings change quickly
+        update_ratings(
+            &mut white,
+            &mut black,
+            &CwChessGameOver::BlackResigns,
+            &glicko2,
+            1,
+        );
+        assert_eq!(white.rating, 1662);
+        assert_eq!(black.rating, 1338);
+        let white_glicko2 = white.glicko2.clone().unwrap();
+        assert_eq!(white_glicko2.deviation, 290);
+        assert_eq!(white_glicko2.last_block, 1);
+        // within 1e-9 of floating point implementation
+        assert!((white_glicko2.volatility as i128 - 59_999_675_372).abs() <= 1_000);
+        assert_eq!(black.glicko2.as_ref().unwrap().deviation, 290);
+        assert!(white.is_provisional());
+
+        // draw moves ratings toward each other, and deviation decreases
+        update_ratings(
+            &mut white,
+            &mut black,
+            &CwChessGameOver::Stalemate,
+            &glicko2,
+            1,
+        );
+        assert_eq!(white.rating, 1577);
+        assert_eq!(black.rating, 1423);
+        assert_eq!(white.glicko2.as_ref().unwrap().deviation, 260);
+    }
+
+    #[test]
+    fn test_glicko2_inactivity() {
+        let player = PlayerRating {
+            games: 100,
+            glicko2: Some(Glicko2 {
+                deviation: 50,
+                last_block: 1,
+                volatility: 60_000_000_000,
+            }),
+            ..PlayerRating::new(Addr::unchecked("player"), CwChessCategory::Blitz)
+        };
+        let opponent = PlayerRating {
+            address: Addr::unchecked("opponent"),
+            ..player.clone()
+        };
+        assert!(!player.is_provisional());
+        // deviation grows with rating periods since last game
+        let (_, active) = glicko2_rating(&player, &opponent, SCALE, 1, 1000);
+        let (_, inactive) = glicko2_rating(&player, &opponent, SCALE, 100_001, 1000);
+        assert_eq!(active.deviation, 51);
+        assert!(inactive.deviation > 100);
+        // and is never more than initial deviation
+        let (_, inactive) = glicko2_rating(&player, &opponent, SCALE, 100_000_001, 1000);
+        assert!(inactive.deviation <= 350);
+    }
+}
@@ -1,17 +1,33 @@
-use cosmwasm_std::{Addr, StdResult, Storage};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cosmwasm_std::{Addr, BlockInfo, StdResult, Storage};
+use cw20::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::iter::Peekable;
 
-use crate::cwchess::{CwChessColor, CwChessGame};
+use crate::cwchess::{
+    CwChessCategory, CwChessClock, CwChessColor, CwChessGame, CwChessGameOver, CwChessTimeControl,
+    CwChessVariant,
+};
+use crate::rating::{
+    Glicko2, RatingSystem, INITIAL_RATING, PROVISIONAL_DEVIATION, PROVISIONAL_GAMES,
+};
+use crate::wager::Wager;
 
 // STATE
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct State {
+    // cw20 token contracts accepted for wagers
+    #[serde(default)]
+    pub cw20_tokens: Vec<Addr>,
+    // blocks without a move before waiting player can claim win by abandonment
+    #[serde(default)]
+    pub inactivity_blocks: Option<u64>,
     pub owner: Addr,
+    #[serde(default)]
+    pub rating_system: RatingSystem,
 }
 
 pub const STATE: Item<State> = Item::new("state");
@@ -24,9 +40,37 @@ pub struct Challenge {
     pub block_created: u64,
     pub block_limit: Option<u64>,
     pub challenge_id: u64,
+    #[serde(default)]
+    pub clock: CwChessClock,
     pub created_by: Addr,
+    pub delay_blocks: Option<u64>,
+    // challenge can't be accepted after this height or time, None for no expiration
+    pub expires_at: Option<Expiration>,
+    pub first_move_limit: Option<u64>,
+    pub increment_blocks: Option<u64>,
+    pub move_limit: Option<u64>,
     pub play_as: Option<CwChessColor>,
     pub opponent: Option<Addr>,
+    // ratings are updated when rated games end
+    #[serde(default)]
+    pub rated: bool,
+    // game this challenge is a rematch of
+    pub rematch_of: Option<u64>,
+    // starting position in FEN, None for standard starting position
+    pub start_fen: Option<String>,
+    #[serde(default)]
+    pub variant: CwChessVariant,
+    // amount staked by creator, acceptor must match
+    pub wager: Option<Wager>,
+}
+
+impl Challenge {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        match &self.expires_at {
+            Some(expires_at) => expires_at.is_expired(block),
+            None => false,
+        }
+    }
 }
 
 pub const CHALLENGE_ID: Item<u64> = Item::new("challenge_id");
@@ -39,12 +83,15 @@ pub fn next_challenge_id(store: &mut dyn Storage) -> StdResult<u64> {
 
 pub struct ChallengeIndexes<'a> {
     pub created_by: MultiIndex<'a, Addr, Challenge, u64>,
+    // see expiration_key, for pruning expired challenges
+    pub expires_at: MultiIndex<'a, (String, u64), Challenge, u64>,
     pub opponent: MultiIndex<'a, Addr, Challenge, u64>,
 }
 
 impl<'a> IndexList<Challenge> for ChallengeIndexes<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Challenge>> + '_> {
-        let v: Vec<&dyn Index<Challenge>> = vec![&self.created_by, &self.opponent];
+        let v: Vec<&dyn Index<Challenge>> =
+            vec![&self.created_by, &self.expires_at, &self.opponent];
         Box::new(v.into_iter())
     }
 }
@@ -56,6 +103,11 @@ pub fn get_challenges_map<'a>() -> IndexedMap<'a, u64, Challenge, ChallengeIndex
             "challenges",
             "challenges__created_by",
         ),
+        expires_at: MultiIndex::new(
+            |c: &Challenge| expiration_key(&c.expires_at),
+            "challenges",
+            "challenges__expires_at",
+        ),
         opponent: MultiIndex::new(
             |c: &Challenge| {
                 c.opponent
@@ -69,6 +121,293 @@ pub fn get_challenges_map<'a>() -> IndexedMap<'a, u64, Challenge, ChallengeIndex
     IndexedMap::new("challenges", indexes)
 }
 
+// challenge expiration index key
+// ("height", block height), ("time", nanoseconds) or ("never", 0)
+pub fn expiration_key(expires_at: &Option<Expiration>) -> (String, u64) {
+    match expires_at {
+        Some(Expiration::AtHeight(height)) => ("height".to_string(), *height),
+        Some(Expiration::AtTime(time)) => ("time".to_string(), time.nanos()),
+        _ => ("never".to_string(), 0),
+    }
+}
+
+// SEEKS
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Seek {
+    pub block_created: u64,
+    pub created_by: Addr,
+    pub play_as: Option<CwChessColor>,
+    pub rated: bool,
+    // seeker's rating when seek was created
+    pub rating: u32,
+    // maximum rating difference from opponent, None for any opponent
+    pub rating_range: Option<u32>,
+    // seeks use challenge ids, so game color assignment hashes are unique
+    pub seek_id: u64,
+    pub time_control: CwChessTimeControl,
+    // amount staked by seeker, matching seek must be the same
+    pub wager: Option<Wager>,
+}
+
+impl Seek {
+    // whether another seek with the same pairing key can be paired with this one
+    pub fn is_compatible(&self, other: &Seek) -> bool {
+        let colors_compatible = match (&self.play_as, &other.play_as) {
+            (Some(color1), Some(color2)) => color1 != color2,
+            _ => true,
+        };
+        let difference = self.rating.max(other.rating) - self.rating.min(other.rating);
+        let in_range = |range: Option<u32>| -> bool {
+            match range {
+                Some(range) => difference <= range,
+                None => true,
+            }
+        };
+        let ratings_compatible = in_range(self.rating_range) && in_range(other.rating_range);
+        self.created_by != other.created_by && colors_compatible && ratings_compatible
+    }
+
+    // seeks are only paired with seeks that have the same rated flag, time control
+    // and wager, serialized so matches can be found with an index prefix
+    pub fn pairing_key(&self) -> String {
+        serde_json_wasm::to_string(&(self.rated, &self.time_control, &self.wager))
+            .unwrap_or_default()
+    }
+
+    // challenge from this seek, accepted by other seek
+    pub fn to_challenge(&self, other: &Seek) -> Challenge {
+        let time_control = self.time_control.clone();
+        Challenge {
+            block_created: self.block_created,
+            block_limit: time_control.block_limit,
+            challenge_id: self.seek_id,
+            clock: time_control.clock,
+            created_by: self.created_by.clone(),
+            delay_blocks: time_control.delay_blocks,
+            expires_at: None,
+            first_move_limit: time_control.first_move_limit,
+            increment_blocks: time_control.increment_blocks,
+            move_limit: time_control.move_limit,
+            opponent: Some(other.created_by.clone()),
+            play_as: match (&self.play_as, &other.play_as) {
+                (None, Some(CwChessColor::White)) => Some(CwChessColor::Black),
+                (None, Some(CwChessColor::Black)) => Some(CwChessColor::White),
+                (play_as, _) => play_as.clone(),
+            },
+            rated: self.rated,
+            rematch_of: None,
+            start_fen: None,
+            variant: CwChessVariant::Standard,
+            wager: self.wager.clone(),
+        }
+    }
+}
+
+pub struct SeekIndexes<'a> {
+    // see Seek::pairing_key
+    pub pairing: MultiIndex<'a, String, Seek, u64>,
+}
+
+impl<'a> IndexList<Seek> for SeekIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Seek>> + '_> {
+        let v: Vec<&dyn Index<Seek>> = vec![&self.pairing];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn get_seeks_map<'a>() -> IndexedMap<'a, u64, Seek, SeekIndexes<'a>> {
+    let indexes = SeekIndexes {
+        pairing: MultiIndex::new(|s: &Seek| s.pairing_key(), "seeks", "seeks__pairing"),
+    };
+    IndexedMap::new("seeks", indexes)
+}
+
+// PLAYER RATINGS
+
+// rating and rated game results for one player and category
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerRating {
+    pub address: Addr,
+    pub category: CwChessCategory,
+    pub draws: u64,
+    // rated games played
+    pub games: u64,
+    // None until player's first Glicko-2 rated game
+    pub glicko2: Option<Glicko2>,
+    pub losses: u64,
+    pub rating: u32,
+    pub wins: u64,
+}
+
+impl PlayerRating {
+    pub fn new(address: Addr, category: CwChessCategory) -> PlayerRating {
+        PlayerRating {
+            address,
+            category,
+            draws: 0,
+            games: 0,
+            glicko2: None,
+            losses: 0,
+            rating: INITIAL_RATING,
+            wins: 0,
+        }
+    }
+
+    pub fn is_provisional(&self) -> bool {
+        match &self.glicko2 {
+            Some(glicko2) => glicko2.deviation > PROVISIONAL_DEVIATION,
+            None => self.games < PROVISIONAL_GAMES,
+        }
+    }
+}
+
+pub struct PlayerRatingIndexes<'a> {
+    // category and rating, for leaderboards
+    pub rating: MultiIndex<'a, (String, u32), PlayerRating, (Addr, String)>,
+    // category and rating of players whose rating is no longer provisional,
+    // provisional players are all indexed under an empty category
+    pub established_rating: MultiIndex<'a, (String, u32), PlayerRating, (Addr, String)>,
+}
+
+impl<'a> IndexList<PlayerRating> for PlayerRatingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PlayerRating>> + '_> {
+        let v: Vec<&dyn Index<PlayerRating>> = vec![&self.rating, &self.established_rating];
+        Box::new(v.into_iter())
+    }
+}
+
+// player ratings by address and category
+pub fn get_player_ratings_map<'a>(
+) -> IndexedMap<'a, (&'a Addr, &'a str), PlayerRating, PlayerRatingIndexes<'a>> {
+    let indexes = PlayerRatingIndexes {
+        rating: MultiIndex::new(
+            |r: &PlayerRating| (r.category.as_str().to_string(), r.rating),
+            "player_ratings",
+            "player_ratings__rating",
+        ),
+        established_rating: MultiIndex::new(
+            |r: &PlayerRating| {
+                if r.is_provisional() {
+                    (String::new(), 0)
+                } else {
+                    (r.category.as_str().to_string(), r.rating)
+                }
+            },
+            "player_ratings",
+            "player_ratings__established_rating",
+        ),
+    };
+    IndexedMap::new("player_ratings", indexes)
+}
+
+// player rating with initial rating if they haven't played a rated game in category
+pub fn load_player_rating(
+    store: &dyn Storage,
+    address: &Addr,
+    category: &CwChessCategory,
+) -> StdResult<PlayerRating> {
+    Ok(get_player_ratings_map()
+        .may_load(store, (address, category.as_str()))?
+        .unwrap_or_else(|| PlayerRating::new(address.clone(), category.clone())))
+}
+
+// PLAYER STATS
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ResultCounts {
+    pub draws: u64,
+    pub losses: u64,
+    pub wins: u64,
+}
+
+impl ResultCounts {
+    fn add(&mut self, winner: &Option<CwChessColor>, color: &CwChessColor) {
+        match winner {
+            Some(winner) if winner == color => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+// wins or losses by how game ended
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DecisiveCounts {
+    pub abandonment: u64,
+    pub checkmate: u64,
+    pub resignation: u64,
+    pub timeout: u64,
+}
+
+// draws by how game ended
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DrawCounts {
+    // draw offer accepted
+    pub accepted: u64,
+    // draw claimed, or automatic draw by repetition or move count
+    pub declared: u64,
+    pub insufficient_material: u64,
+    pub stalemate: u64,
+}
+
+// results of all games, rated and casual
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerStats {
+    // games aborted before both players moved, not counted in other results
+    pub aborted: u64,
+    pub as_black: ResultCounts,
+    pub as_white: ResultCounts,
+    pub draws: DrawCounts,
+    pub losses: DecisiveCounts,
+    pub total: ResultCounts,
+    pub wins: DecisiveCounts,
+}
+
+impl PlayerStats {
+    // add result of game where player played color
+    pub fn add(&mut self, status: &CwChessGameOver, color: &CwChessColor) {
+        let winner = status.winner();
+        let decisive = match &winner {
+            Some(winner) if winner == color => &mut self.wins,
+            _ => &mut self.losses,
+        };
+        match status {
+            CwChessGameOver::Aborted => {
+                self.aborted += 1;
+                return;
+            }
+            CwChessGameOver::BlackAbandons | CwChessGameOver::WhiteAbandons => {
+                decisive.abandonment += 1
+            }
+            CwChessGameOver::BlackCheckmates | CwChessGameOver::WhiteCheckmates => {
+                decisive.checkmate += 1
+            }
+            CwChessGameOver::BlackResigns | CwChessGameOver::WhiteResigns => {
+                decisive.resignation += 1
+            }
+            CwChessGameOver::BlackTimeout | CwChessGameOver::WhiteTimeout => decisive.timeout += 1,
+            CwChessGameOver::DrawAccepted => self.draws.accepted += 1,
+            CwChessGameOver::DrawDeclared => self.draws.declared += 1,
+            CwChessGameOver::InsufficientMaterial => self.draws.insufficient_material += 1,
+            CwChessGameOver::Stalemate => self.draws.stalemate += 1,
+        }
+        match color {
+            CwChessColor::White => self.as_white.add(&winner, color),
+            CwChessColor::Black => self.as_black.add(&winner, color),
+        }
+        self.total.add(&winner, color);
+    }
+}
+
+pub const PLAYER_STATS: Map<&Addr, PlayerStats> = Map::new("player_stats");
+
 // GAMES
 
 pub const GAME_ID: Item<u64> = Item::new("game_id");
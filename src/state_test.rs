@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::state::merge_iters;
+    use crate::cwchess::{CwChessColor, CwChessGameOver, CwChessTimeControl};
+    use crate::state::{merge_iters, DecisiveCounts, PlayerStats, ResultCounts, Seek};
+    use crate::wager::Wager;
+    use cosmwasm_std::{coin, Addr};
 
     #[test]
     fn test_merge_iters() {
@@ -37,4 +40,110 @@ mod tests {
 
         assert_eq!(merged, vec![1, 3, 5]);
     }
+
+    #[test]
+    fn test_player_stats() {
+        let mut stats = PlayerStats::default();
+        stats.add(&CwChessGameOver::WhiteCheckmates, &CwChessColor::White);
+        stats.add(&CwChessGameOver::WhiteTimeout, &CwChessColor::Black);
+        stats.add(&CwChessGameOver::BlackResigns, &CwChessColor::Black);
+        stats.add(&CwChessGameOver::Stalemate, &CwChessColor::Black);
+        stats.add(&CwChessGameOver::Aborted, &CwChessColor::White);
+
+        assert_eq!(stats.aborted, 1);
+        assert_eq!(
+            stats.wins,
+            DecisiveCounts {
+                checkmate: 1,
+                timeout: 1,
+                ..DecisiveCounts::default()
+            }
+        );
+        assert_eq!(stats.losses.resignation, 1);
+        assert_eq!(stats.draws.stalemate, 1);
+        assert_eq!(
+            stats.as_white,
+            ResultCounts {
+                draws: 0,
+                losses: 0,
+                wins: 1,
+            }
+        );
+        assert_eq!(
+            stats.as_black,
+            ResultCounts {
+                draws: 1,
+                losses: 1,
+                wins: 1,
+            }
+        );
+        assert_eq!(
+            stats.total,
+            ResultCounts {
+                draws: 1,
+                losses: 1,
+                wins: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_seek_pairing() {
+        let seek = Seek {
+            block_created: 1,
+            created_by: Addr::unchecked("seeker"),
+            play_as: Some(CwChessColor::White),
+            rated: true,
+            rating: 1500,
+            rating_range: Some(100),
+            seek_id: 1,
+            time_control: CwChessTimeControl {
+                block_limit: Some(300),
+                ..CwChessTimeControl::default()
+            },
+            wager: Some(Wager::Native(coin(100, "ujuno"))),
+        };
+        let other = Seek {
+            created_by: Addr::unchecked("other"),
+            play_as: None,
+            rating: 1600,
+            rating_range: None,
+            seek_id: 2,
+            ..seek.clone()
+        };
+        assert_eq!(seek.pairing_key(), other.pairing_key());
+        assert!(seek.is_compatible(&other));
+        // color, rating range and player are checked
+        let same_color = Seek {
+            play_as: Some(CwChessColor::White),
+            ..other.clone()
+        };
+        assert!(!seek.is_compatible(&same_color));
+        let out_of_range = Seek {
+            rating: 1601,
+            ..other.clone()
+        };
+        assert!(!seek.is_compatible(&out_of_range));
+        assert!(!seek.is_compatible(&seek));
+        // rated flag, time control and wager are part of pairing key
+        let casual = Seek {
+            rated: false,
+            ..other.clone()
+        };
+        assert_ne!(seek.pairing_key(), casual.pairing_key());
+        let increment = Seek {
+            time_control: CwChessTimeControl {
+                block_limit: Some(300),
+                increment_blocks: Some(5),
+                ..CwChessTimeControl::default()
+            },
+            ..other.clone()
+        };
+        assert_ne!(seek.pairing_key(), increment.pairing_key());
+        let wager = Seek {
+            wager: Some(Wager::Native(coin(101, "ujuno"))),
+            ..other
+        };
+        assert_ne!(seek.pairing_key(), wager.pairing_key());
+    }
 }
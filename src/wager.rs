@@ -0,0 +1,68 @@
+use cosmwasm_std::{to_binary, Addr, BankMsg, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::ContractError;
+
+// amount staked by each player, held by contract until game over
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Wager {
+    // cw20 tokens, sent through Receive hook
+    Cw20(Cw20CoinVerified),
+    // native coin, sent as funds
+    Native(Coin),
+}
+
+impl Wager {
+    // wager is optional, but only one native coin may be staked
+    pub fn from_funds(funds: &[Coin]) -> Result<Option<Wager>, ContractError> {
+        match funds {
+            [] => Ok(None),
+            [coin] => Ok(Some(Wager::Native(coin.clone()))),
+            _ => Err(ContractError::InvalidWager {}),
+        }
+    }
+
+    pub fn amount(&self) -> Uint128 {
+        match self {
+            Wager::Cw20(coin) => coin.amount,
+            Wager::Native(coin) => coin.amount,
+        }
+    }
+
+    // message to send amount of wagered token to recipient
+    pub fn send_msg(&self, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        Ok(match self {
+            Wager::Cw20(coin) => WasmMsg::Execute {
+                contract_addr: coin.address.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+            Wager::Native(coin) => BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    amount,
+                    denom: coin.denom.clone(),
+                }],
+            }
+            .into(),
+        })
+    }
+}
+
+impl fmt::Display for Wager {
+    // same format as native coins, cw20 tokens use contract address as denom
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Wager::Cw20(coin) => write!(f, "{}{}", coin.amount, coin.address),
+            Wager::Native(coin) => write!(f, "{}", coin),
+        }
+    }
+}